@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// parsing arbitrary bytes must return an error instead of panicking
+fuzz_target!(|data: &[u8]| {
+    let _ = mneb::MNEBFile::from_bytes(data);
+});
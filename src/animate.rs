@@ -1,123 +1,133 @@
-use crate::mneb::*;
 use macroquad::prelude::*;
+use mneb::animation::NodeState;
+use mneb::*;
 
 const FONT_SIZE: f32 = 20.0f32;
 
-pub fn animate_files(mneb_files: Vec<(String, MNEBFile)>, framerate: f32) {
-    let conf = Conf {
-        window_title: "MNEB Renderer".to_string(),
-        ..Default::default()
-    };
-
-    macroquad::Window::from_config(conf, async move {
-        let mut current_anim_index = 0;
-        let mut current_frame = 0.0f32;
-        let mut camera_pos = Vec2::splat(0.0);
-        let mut zoom = 0.001f32;
-        let mut last_mouse_pos: Vec2 = mouse_position().into();
-        let mut paused = false;
-
-        loop {
-            let (filename, mneb_file) = &mneb_files[current_anim_index];
+/// The current frame of a file, and the in/out range it's restricted to.
+struct Playback {
+    current_frame: f32,
+    in_frame: Option<f32>,
+    out_frame: Option<f32>,
+}
 
-            /* config updates */
+impl Playback {
+    /// The range to play for a file with `frame_count` frames, from `start` up to `end`.
+    fn range(&self, frame_count: f32) -> (f32, f32) {
+        let start = self.in_frame.unwrap_or(0.0).min(frame_count);
+        let end = self.out_frame.unwrap_or(frame_count).min(frame_count);
+        (start, end)
+    }
 
-            clear_background(WHITE);
+    fn has_range(&self) -> bool {
+        self.in_frame.is_some() || self.out_frame.is_some()
+    }
 
-            // handle zoom
-            let wheel = mouse_wheel();
-            if wheel.1 != 0.0 {
-                let zoom_factor = 1.1f32;
-                if wheel.1 > 0.0 {
-                    zoom *= zoom_factor;
-                } else {
-                    zoom /= zoom_factor;
-                }
-            }
+    fn set_in(&mut self) {
+        self.in_frame = Some(self.current_frame.floor());
+        if self.out_frame.is_some_and(|out| out <= self.current_frame) {
+            self.out_frame = None;
+        }
+    }
 
-            // mouse input
-            let mouse_pos: Vec2 = mouse_position().into();
-            let mouse_delta = mouse_pos - last_mouse_pos;
-            last_mouse_pos = mouse_pos;
+    fn set_out(&mut self) {
+        self.out_frame = Some(self.current_frame.ceil());
+        if self
+            .in_frame
+            .is_some_and(|in_frame| in_frame >= self.current_frame)
+        {
+            self.in_frame = None;
+        }
+    }
 
-            if is_mouse_button_down(MouseButton::Left) {
-                camera_pos -= mouse_delta / (zoom * screen_height());
-            }
+    fn rewind(&mut self, mneb_file: &MNEBFile) {
+        self.current_frame = self.range(mneb_file.frame_count as f32).0;
+    }
 
-            // pause
-            if is_key_pressed(KeyCode::Space) {
-                paused = !paused;
-            }
+    /// Advances by `delta` frames. Looping files and in/out ranges wrap around, while other
+    /// files hold on their last frame. Returns `true` once a file that doesn't loop has ended.
+    fn advance(&mut self, mneb_file: &MNEBFile, delta: f32) -> bool {
+        let (start, end) = self.range(mneb_file.frame_count as f32);
+        if end <= start {
+            self.current_frame = start;
+            return !mneb_file.is_looped;
+        }
 
-            // update frame
-            if !paused {
-                current_frame += framerate * get_frame_time();
+        self.current_frame += delta;
 
-                // if done, move to the next one
-                if current_frame >= mneb_file.frame_count as f32 {
-                    current_frame = 0.0;
-                    current_anim_index = (current_anim_index + 1) % mneb_files.len();
-                }
+        if mneb_file.is_looped || self.has_range() {
+            if self.current_frame < start || self.current_frame >= end {
+                self.current_frame = start + (self.current_frame - start).rem_euclid(end - start);
             }
 
-            /* drawing */
-
-            let camera = Camera2D {
-                target: camera_pos,
-                zoom: vec2(zoom * (screen_height() / screen_width()), -zoom),
-                ..Default::default()
-            };
-            set_camera(&camera);
-
-            animate_curves(&mneb_file.curves, zoom, current_frame);
-
-            // render text
-            set_default_camera();
+            false
+        } else if self.current_frame >= end - 1.0 {
+            self.current_frame = (end - 1.0).max(start);
+            true
+        } else {
+            false
+        }
+    }
+}
 
-            draw_text(
-                &format!(
-                    "Playing {}/{}: {}",
-                    current_anim_index,
-                    mneb_files.len(),
-                    &filename
-                ),
-                20.0,
-                15.0,
-                FONT_SIZE,
-                BLACK,
-            );
-            draw_text(
-                "Space: Pause | Left-click: Pan | Scroll: Zoom",
-                20.0,
-                30.0,
-                FONT_SIZE,
-                BLACK,
-            );
-            draw_text(
-                &format!(
-                    "Frame: {:.0} / {}",
-                    current_frame, mneb_file.frame_count as f32
-                ),
-                20.0,
-                45.0,
-                FONT_SIZE,
-                BLACK,
-            );
-            draw_text(&format!("Zoom: {:.4}", zoom), 20.0, 60.0, FONT_SIZE, BLACK);
+/// Plays several files one after another. Files that don't loop advance to the next file
+/// when they end, while looping files keep playing until the next file is picked.
+pub fn animate_files(
+    mneb_files: Vec<(String, MNEBFile)>,
+    framerate: f32,
+    show_control_polygon: bool,
+    in_frame: Option<u16>,
+    out_frame: Option<u16>,
+) {
+    play(
+        mneb_files,
+        framerate,
+        show_control_polygon,
+        in_frame,
+        out_frame,
+        true,
+    );
+}
 
-            next_frame().await
-        }
-    });
+pub fn animate_file(
+    mneb_file: MNEBFile,
+    framerate: f32,
+    filename: String,
+    show_control_polygon: bool,
+    in_frame: Option<u16>,
+    out_frame: Option<u16>,
+) {
+    play(
+        vec![(filename, mneb_file)],
+        framerate,
+        show_control_polygon,
+        in_frame,
+        out_frame,
+        false,
+    );
 }
 
-pub fn animate_file(mneb_file: MNEBFile, framerate: f32, filename: String) {
+fn play(
+    mneb_files: Vec<(String, MNEBFile)>,
+    framerate: f32,
+    mut show_control_polygon: bool,
+    in_frame: Option<u16>,
+    out_frame: Option<u16>,
+    is_playlist: bool,
+) {
     let conf = Conf {
         window_title: "MNEB Renderer".to_string(),
         ..Default::default()
     };
 
     macroquad::Window::from_config(conf, async move {
-        let mut current_frame = 0.0f32;
+        let mut current_anim_index = 0;
+        let mut playback = Playback {
+            current_frame: 0.0,
+            in_frame: in_frame.map(f32::from),
+            out_frame: out_frame.map(f32::from),
+        };
+        playback.rewind(&mneb_files[0].1);
         let mut camera_pos = Vec2::splat(0.0);
         let mut zoom = 0.001f32;
         let mut last_mouse_pos: Vec2 = mouse_position().into();
@@ -153,12 +163,55 @@ pub fn animate_file(mneb_file: MNEBFile, framerate: f32, filename: String) {
                 paused = !paused;
             }
 
+            // control polygon overlay
+            if is_key_pressed(KeyCode::C) {
+                show_control_polygon = !show_control_polygon;
+            }
+
+            // in/out range
+            if is_key_pressed(KeyCode::I) {
+                playback.set_in();
+            }
+
+            if is_key_pressed(KeyCode::O) {
+                playback.set_out();
+            }
+
+            if is_key_pressed(KeyCode::R) {
+                playback.in_frame = None;
+                playback.out_frame = None;
+            }
+
+            // switch files
+            if is_playlist {
+                let num_files = mneb_files.len();
+                if is_key_pressed(KeyCode::Right) {
+                    current_anim_index = (current_anim_index + 1) % num_files;
+                    playback.rewind(&mneb_files[current_anim_index].1);
+                }
+
+                if is_key_pressed(KeyCode::Left) {
+                    current_anim_index = (current_anim_index + num_files - 1) % num_files;
+                    playback.rewind(&mneb_files[current_anim_index].1);
+                }
+            }
+
             // update frame
             if !paused {
-                current_frame =
-                    (current_frame + framerate * get_frame_time()) % mneb_file.frame_count as f32;
+                let finished = playback.advance(
+                    &mneb_files[current_anim_index].1,
+                    framerate * get_frame_time(),
+                );
+
+                // if done, move to the next one
+                if finished && is_playlist {
+                    current_anim_index = (current_anim_index + 1) % mneb_files.len();
+                    playback.rewind(&mneb_files[current_anim_index].1);
+                }
             }
 
+            let (filename, mneb_file) = &mneb_files[current_anim_index];
+
             /* drawing */
 
             let camera = Camera2D {
@@ -168,99 +221,173 @@ pub fn animate_file(mneb_file: MNEBFile, framerate: f32, filename: String) {
             };
             set_camera(&camera);
 
-            animate_curves(&mneb_file.curves, zoom, current_frame);
+            let num_inactive = animate_curves(
+                &mneb_file.curves,
+                zoom,
+                playback.current_frame,
+                show_control_polygon,
+            );
 
             // render text
             set_default_camera();
 
-            draw_text(
-                &format!("Playing: {}", &filename),
-                20.0,
-                15.0,
-                FONT_SIZE,
-                BLACK,
-            );
-            draw_text(
-                "Space: Pause | Left-click: Pan | Scroll: Zoom",
-                20.0,
-                30.0,
-                FONT_SIZE,
-                BLACK,
-            );
-            draw_text(
-                &format!(
-                    "Frame: {:.0} / {}",
-                    current_frame, mneb_file.frame_count as f32
-                ),
-                20.0,
-                45.0,
-                FONT_SIZE,
-                BLACK,
+            let mut lines = Vec::new();
+            if is_playlist {
+                lines.push(format!(
+                    "Playing {}/{}: {}",
+                    current_anim_index + 1,
+                    mneb_files.len(),
+                    &filename
+                ));
+            } else {
+                lines.push(format!("Playing: {}", &filename));
+            }
+
+            lines.push(
+                "Space: Pause | C: Control polygon | Left-click: Pan | Scroll: Zoom".to_string(),
             );
-            draw_text(&format!("Zoom: {:.4}", zoom), 20.0, 60.0, FONT_SIZE, BLACK);
+            if is_playlist {
+                lines.push(
+                    "I/O: Set in/out frame | R: Clear range | Left/Right: Switch file".to_string(),
+                );
+            } else {
+                lines.push("I/O: Set in/out frame | R: Clear range".to_string());
+            }
+
+            lines.push(format!(
+                "Frame: {:.0} / {}{}",
+                playback.current_frame,
+                mneb_file.frame_count,
+                if mneb_file.is_looped { " (looped)" } else { "" }
+            ));
+            if playback.has_range() {
+                let (start, end) = playback.range(mneb_file.frame_count as f32);
+                lines.push(format!("Range: {} - {}", start, end));
+            }
+
+            lines.push(format!("Zoom: {:.4}", zoom));
+            lines.push(format!("Inactive nodes: {}", num_inactive));
+
+            for (i, line) in lines.iter().enumerate() {
+                draw_text(line, 20.0, 15.0 + 15.0 * i as f32, FONT_SIZE, BLACK);
+            }
 
             next_frame().await
         }
     });
 }
 
-fn animate_curves(curves: &[Curve], zoom: f32, current_frame: f32) {
+/// Draws the curves at `current_frame`, returning how many nodes are currently inactive.
+fn animate_curves(
+    curves: &[Curve],
+    zoom: f32,
+    current_frame: f32,
+    show_control_polygon: bool,
+) -> usize {
+    let thickness = 2.0 / (zoom * 500.0);
+    let mut num_inactive = 0;
+
     for curve in curves.iter() {
-        let mut current_positions: Vec<Vec2> = curve
-            .control_points
-            .iter()
-            .map(|point| vec2(point.x as f32, point.y as f32))
-            .collect();
+        let pose = curve.pose(current_frame);
+        num_inactive += pose.num_inactive();
 
-        for key_set in &curve.key_frame_sets {
-            let idx = key_set.node_index as usize;
-            if idx < current_positions.len()
-                && let Some((nx, ny)) = interpolate(&key_set.key_frames, current_frame)
-            {
-                current_positions[idx] = vec2(nx, ny);
-            }
-        }
+        let current_positions: Vec<Vec2> =
+            pose.positions.iter().map(|&(x, y)| vec2(x, y)).collect();
 
-        // draw lines
-        if current_positions.len() > 1 {
-            for i in 0..current_positions.len() - 1 {
-                draw_line(
-                    current_positions[i].x,
-                    current_positions[i].y,
-                    current_positions[i + 1].x,
-                    current_positions[i + 1].y,
-                    2.0 / (zoom * 500.0), // line thickness
-                    BLUE,
-                );
+        // draw the curve itself
+        let outline: Vec<Vec2> = pose
+            .outline(&curve.knots)
+            .into_iter()
+            .map(|(x, y)| vec2(x, y))
+            .collect();
+        draw_polyline(&outline, thickness, BLUE);
+
+        // draw the control polygon and points
+        if show_control_polygon {
+            draw_polyline(&current_positions, thickness / 2.0, GRAY);
+
+            for (pos, state) in current_positions.iter().zip(&pose.states) {
+                match state {
+                    // not animated
+                    None => draw_circle(pos.x, pos.y, thickness * 1.5, RED),
+                    Some(NodeState::Active(..)) => {
+                        draw_circle(pos.x, pos.y, thickness * 1.5, GREEN)
+                    }
+                    Some(NodeState::Inactive) => {
+                        draw_circle_lines(pos.x, pos.y, thickness * 2.0, thickness / 2.0, GRAY)
+                    }
+                }
             }
         }
+    }
+
+    num_inactive
+}
 
-        // // draw points
-        // for pos in &current_positions {
-        //     draw_circle(pos.x, pos.y, 3.0, RED);
-        // }
+fn draw_polyline(points: &[Vec2], thickness: f32, color: Color) {
+    for segment in points.windows(2) {
+        draw_line(
+            segment[0].x,
+            segment[0].y,
+            segment[1].x,
+            segment[1].y,
+            thickness,
+            color,
+        );
     }
 }
-fn interpolate(keyframes: &[KeyFrame], current_frame: f32) -> Option<(f32, f32)> {
-    if keyframes.is_empty() {
-        return None;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playback(current_frame: f32, in_frame: Option<f32>, out_frame: Option<f32>) -> Playback {
+        Playback {
+            current_frame,
+            in_frame,
+            out_frame,
+        }
+    }
+
+    #[test]
+    fn looped_files_wrap_around() {
+        let mneb_file = MNEBFile::new(Vec::new(), 10, true);
+        let mut playback = playback(9.5, None, None);
+
+        assert!(!playback.advance(&mneb_file, 1.0));
+        assert_eq!(playback.current_frame, 0.5);
     }
 
-    if keyframes.len() == 1 {
-        return Some((keyframes[0].x as f32, keyframes[0].y as f32));
+    #[test]
+    fn other_files_hold_their_last_frame() {
+        let mneb_file = MNEBFile::new(Vec::new(), 10, false);
+        let mut playback = playback(7.0, None, None);
+
+        assert!(!playback.advance(&mneb_file, 1.0));
+        assert_eq!(playback.current_frame, 8.0);
+
+        assert!(playback.advance(&mneb_file, 1.5));
+        assert_eq!(playback.current_frame, 9.0);
     }
 
-    for i in 0..keyframes.len() - 1 {
-        let start = &keyframes[i];
-        let end = &keyframes[i + 1];
+    #[test]
+    fn ranges_wrap_around_even_without_looping() {
+        let mneb_file = MNEBFile::new(Vec::new(), 100, false);
+        let mut playback = playback(19.0, Some(10.0), Some(20.0));
 
-        if current_frame >= start.frame as f32 && current_frame <= end.frame as f32 {
-            let t = (current_frame - start.frame as f32) / (end.frame as f32 - start.frame as f32);
-            let x = start.x as f32 + t * (end.x as f32 - start.x as f32);
-            let y = start.y as f32 + t * (end.y as f32 - start.y as f32);
-            return Some((x, y));
-        }
+        assert!(!playback.advance(&mneb_file, 2.0));
+        assert_eq!(playback.current_frame, 11.0);
+
+        playback.rewind(&mneb_file);
+        assert_eq!(playback.current_frame, 10.0);
     }
 
-    keyframes.last().map(|k| (k.x as f32, k.y as f32))
+    #[test]
+    fn empty_ranges() {
+        let mut playback = playback(5.0, Some(30.0), Some(20.0));
+
+        assert!(playback.advance(&MNEBFile::new(Vec::new(), 100, false), 1.0));
+        assert_eq!(playback.current_frame, 30.0);
+        assert!(!playback.advance(&MNEBFile::new(Vec::new(), 100, true), 1.0));
+    }
 }
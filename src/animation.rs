@@ -0,0 +1,211 @@
+//! Posing curves at a given frame by interpolating their key frames.
+
+use crate::mneb::{Curve, KeyFrame};
+use crate::spline;
+
+/// How finely to sample splines, per control point.
+const SEGMENTS_PER_POINT: usize = 16;
+
+/// The state of an animated node at a given frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeState {
+    /// The node is at the given position.
+    Active(f32, f32),
+    /// The node is switched off, and sits at its rest control point.
+    Inactive,
+}
+
+/// Finds the state of a node at `current_frame`.
+///
+/// An inactive key frame switches the node off until the next active key frame, and while it's
+/// off the node falls back to its rest position. Positions are only interpolated between two
+/// active key frames, so an active key frame followed by an inactive one holds its position.
+///
+/// Returns `None` if there are no key frames.
+pub fn interpolate(keyframes: &[KeyFrame], current_frame: f32) -> Option<NodeState> {
+    let state = |k: &KeyFrame| {
+        if k.is_active {
+            NodeState::Active(k.x as f32, k.y as f32)
+        } else {
+            NodeState::Inactive
+        }
+    };
+
+    if keyframes.is_empty() {
+        return None;
+    }
+
+    if keyframes.len() == 1 {
+        return Some(state(&keyframes[0]));
+    }
+
+    for i in 0..keyframes.len() - 1 {
+        let start = &keyframes[i];
+        let end = &keyframes[i + 1];
+
+        if current_frame >= start.frame as f32 && current_frame <= end.frame as f32 {
+            if current_frame >= end.frame as f32 {
+                return Some(state(end));
+            }
+
+            if !start.is_active || !end.is_active {
+                return Some(state(start));
+            }
+
+            let t = (current_frame - start.frame as f32) / (end.frame as f32 - start.frame as f32);
+            let x = start.x as f32 + t * (end.x as f32 - start.x as f32);
+            let y = start.y as f32 + t * (end.y as f32 - start.y as f32);
+            return Some(NodeState::Active(x, y));
+        }
+    }
+
+    keyframes.last().map(state)
+}
+
+/// The nodes of a curve at a given frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// The x and y position of each node.
+    pub positions: Vec<(f32, f32)>,
+    /// The state of each node, or `None` if it isn't animated.
+    pub states: Vec<Option<NodeState>>,
+}
+
+impl Pose {
+    /// How many nodes are switched off.
+    pub fn num_inactive(&self) -> usize {
+        self.states
+            .iter()
+            .filter(|state| matches!(state, Some(NodeState::Inactive)))
+            .count()
+    }
+
+    /// The shape of the posed curve, as a polyline. This is the B-spline if `knots` describe
+    /// one, and the control polygon otherwise.
+    pub fn outline(&self, knots: &[f32]) -> Vec<(f32, f32)> {
+        let spline = spline::tessellate(
+            &self.positions,
+            knots,
+            SEGMENTS_PER_POINT * self.positions.len(),
+        );
+
+        if spline.is_empty() {
+            self.positions.clone()
+        } else {
+            spline
+        }
+    }
+}
+
+impl Curve {
+    /// Poses the curve at `current_frame`. Key frame sets for nodes that don't exist are ignored.
+    pub fn pose(&self, current_frame: f32) -> Pose {
+        let mut positions = self.rest_positions();
+        let mut states = vec![None; positions.len()];
+
+        for key_set in &self.key_frame_sets {
+            let idx = key_set.node_index as usize;
+            if idx < positions.len()
+                && let Some(state) = interpolate(&key_set.key_frames, current_frame)
+            {
+                if let NodeState::Active(x, y) = state {
+                    positions[idx] = (x, y);
+                }
+
+                states[idx] = Some(state);
+            }
+        }
+
+        Pose { positions, states }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::{ControlPoint, KeyFrameSet};
+
+    fn active(frame: u16, x: i16, y: i16) -> KeyFrame {
+        KeyFrame::new(frame, true, x, y)
+    }
+
+    fn inactive(frame: u16) -> KeyFrame {
+        KeyFrame::new(frame, false, 0, 0)
+    }
+
+    #[test]
+    fn active_key_frames_are_interpolated() {
+        let key_frames = [active(0, 0, 0), active(10, 100, -50)];
+
+        assert_eq!(
+            interpolate(&key_frames, 0.0),
+            Some(NodeState::Active(0.0, 0.0))
+        );
+        assert_eq!(
+            interpolate(&key_frames, 2.5),
+            Some(NodeState::Active(25.0, -12.5))
+        );
+        assert_eq!(
+            interpolate(&key_frames, 10.0),
+            Some(NodeState::Active(100.0, -50.0))
+        );
+        // past the last key frame, the node holds it
+        assert_eq!(
+            interpolate(&key_frames, 20.0),
+            Some(NodeState::Active(100.0, -50.0))
+        );
+    }
+
+    #[test]
+    fn inactive_key_frames_switch_nodes_off() {
+        let key_frames = [active(0, 10, 20), inactive(10), active(20, 30, 40)];
+
+        // an active key frame followed by an inactive one holds its position
+        assert_eq!(
+            interpolate(&key_frames, 5.0),
+            Some(NodeState::Active(10.0, 20.0))
+        );
+        assert_eq!(interpolate(&key_frames, 10.0), Some(NodeState::Inactive));
+        assert_eq!(interpolate(&key_frames, 15.0), Some(NodeState::Inactive));
+        assert_eq!(
+            interpolate(&key_frames, 20.0),
+            Some(NodeState::Active(30.0, 40.0))
+        );
+    }
+
+    #[test]
+    fn no_key_frames() {
+        assert_eq!(interpolate(&[], 0.0), None);
+        assert_eq!(interpolate(&[inactive(5)], 0.0), Some(NodeState::Inactive));
+    }
+
+    #[test]
+    fn inactive_nodes_sit_at_rest() {
+        let mut curve = Curve::new(
+            "a",
+            vec![ControlPoint::new(1, 2, 0, 0), ControlPoint::new(3, 4, 0, 0)],
+            Vec::new(),
+        );
+        curve.key_frame_sets = vec![
+            KeyFrameSet::new(0, vec![active(0, 50, 60), active(10, 50, 60)]),
+            KeyFrameSet::new(1, vec![active(0, 70, 80), inactive(5)]),
+            // there is no node 2, so this is ignored
+            KeyFrameSet::new(2, vec![active(0, 90, 90)]),
+        ];
+
+        let pose = curve.pose(2.0);
+        assert_eq!(pose.positions, [(50.0, 60.0), (70.0, 80.0)]);
+        assert_eq!(pose.num_inactive(), 0);
+
+        let pose = curve.pose(5.0);
+        assert_eq!(pose.positions, [(50.0, 60.0), (3.0, 4.0)]);
+        assert_eq!(
+            pose.states,
+            [
+                Some(NodeState::Active(50.0, 60.0)),
+                Some(NodeState::Inactive)
+            ]
+        );
+        assert_eq!(pose.num_inactive(), 1);
+    }
+}
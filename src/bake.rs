@@ -0,0 +1,222 @@
+//! Baking animations into the posed positions of every node on every frame, for tools that
+//! can't interpolate key frames themselves.
+
+use crate::animation::NodeState;
+use crate::error::BakeError;
+use crate::mneb::{KeyFrame, KeyFrameSet, MNEBFile};
+use serde::Serialize;
+
+/// Why a node is where it is on a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointState {
+    /// The node isn't animated, and sits at its control point.
+    Rest,
+    /// The node is placed by its key frames.
+    Active,
+    /// The node is switched off by its key frames, and sits at its control point.
+    Inactive,
+}
+
+/// The position of a node on a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BakedPoint {
+    pub x: f32,
+    pub y: f32,
+    pub state: PointState,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BakedCurve {
+    pub name: String,
+    /// The position of every node, indexed by frame and then by node.
+    pub frames: Vec<Vec<BakedPoint>>,
+}
+
+/// Every curve of a file, posed on every frame.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Baked {
+    pub frame_count: u16,
+    pub is_looped: bool,
+    pub curves: Vec<BakedCurve>,
+}
+
+/// The position of a node on a frame, along with its curve.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BakedRow {
+    pub curve: String,
+    pub frame: u16,
+    pub node_index: u16,
+    pub x: f32,
+    pub y: f32,
+    pub state: PointState,
+}
+
+/// Poses every curve of `mneb_file` on every frame from 0 up to its frame count, the same way
+/// the viewer does. Fails if the file has no frames.
+pub fn bake(mneb_file: &MNEBFile) -> Result<Baked, BakeError> {
+    if mneb_file.frame_count == 0 {
+        return Err(BakeError::NoFrames);
+    }
+
+    let curves = mneb_file
+        .curves
+        .iter()
+        .map(|curve| BakedCurve {
+            name: curve.name.clone(),
+            frames: (0..mneb_file.frame_count)
+                .map(|frame| {
+                    let pose = curve.pose(frame as f32);
+                    pose.positions
+                        .iter()
+                        .zip(&pose.states)
+                        .map(|(&(x, y), state)| BakedPoint {
+                            x,
+                            y,
+                            state: match state {
+                                None => PointState::Rest,
+                                Some(NodeState::Active(..)) => PointState::Active,
+                                Some(NodeState::Inactive) => PointState::Inactive,
+                            },
+                        })
+                        .collect()
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Baked {
+        frame_count: mneb_file.frame_count,
+        is_looped: mneb_file.is_looped,
+        curves,
+    })
+}
+
+impl Baked {
+    /// Lists the position of every node on every frame, by curve, then frame, then node.
+    pub fn rows(&self) -> Vec<BakedRow> {
+        let mut rows = Vec::new();
+
+        for curve in &self.curves {
+            for (frame, points) in curve.frames.iter().enumerate() {
+                for (node_index, point) in points.iter().enumerate() {
+                    rows.push(BakedRow {
+                        curve: curve.name.clone(),
+                        frame: frame as u16,
+                        node_index: node_index as u16,
+                        x: point.x,
+                        y: point.y,
+                        state: point.state,
+                    });
+                }
+            }
+        }
+
+        rows
+    }
+
+    /// Writes the position of every node on every frame as CSV, with a header row.
+    #[cfg(feature = "csv")]
+    pub fn write_csv<W: std::io::Write>(&self, writer: W) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        for row in self.rows() {
+            writer.serialize(row)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Replaces the key frame sets of `mneb_file` with baked ones, which have a key frame on every
+/// frame for every animated node. Positions are rounded to whole pixels, and key frame sets for
+/// nodes that don't exist are dropped.
+///
+/// The file is left as it was if it has no frames, or if a curve has more nodes than key frame
+/// sets can address.
+pub fn bake_key_frames(mneb_file: &mut MNEBFile) -> Result<(), BakeError> {
+    if let Some(curve) = mneb_file
+        .curves
+        .iter()
+        .find(|curve| u16::try_from(curve.control_points.len()).is_err())
+    {
+        return Err(BakeError::TooManyPoints {
+            curve: curve.name.clone(),
+            count: curve.control_points.len(),
+        });
+    }
+
+    let baked = bake(mneb_file)?;
+
+    for (curve, baked_curve) in mneb_file.curves.iter_mut().zip(baked.curves) {
+        let mut key_frame_sets: Vec<KeyFrameSet> = Vec::new();
+
+        for node_index in 0..curve.control_points.len() {
+            let key_frames: Vec<KeyFrame> = baked_curve
+                .frames
+                .iter()
+                .enumerate()
+                .filter_map(|(frame, points)| {
+                    let point = &points[node_index];
+                    match point.state {
+                        PointState::Rest => None,
+                        PointState::Active => Some(KeyFrame::new(
+                            frame as u16,
+                            true,
+                            point.x.round() as i16,
+                            point.y.round() as i16,
+                        )),
+                        PointState::Inactive => Some(KeyFrame::new(frame as u16, false, 0, 0)),
+                    }
+                })
+                .collect();
+
+            if !key_frames.is_empty() {
+                // fits, since the node count was checked above
+                key_frame_sets.push(KeyFrameSet::new(node_index as u16, key_frames));
+            }
+        }
+
+        curve.key_frame_sets = key_frame_sets;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::curves;
+
+    #[test]
+    fn dense_key_frames_keep_the_animation() {
+        let mut mneb_file = curves();
+        let before = bake(&mneb_file).unwrap();
+        bake_key_frames(&mut mneb_file).unwrap();
+
+        for set in &mneb_file.curves[0].key_frame_sets {
+            assert_eq!(set.key_frames.len(), mneb_file.frame_count as usize);
+        }
+
+        // positions are rounded, so compare them to the nearest pixel
+        let after = bake(&mneb_file).unwrap();
+        for (a, b) in before.rows().iter().zip(after.rows()) {
+            assert_eq!(a.state, b.state);
+            assert!((a.x - b.x).abs() <= 0.5 && (a.y - b.y).abs() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn no_frames() {
+        let mut mneb_file = curves();
+        mneb_file.frame_count = 0;
+        let original = mneb_file.clone();
+
+        assert!(matches!(bake(&mneb_file), Err(BakeError::NoFrames)));
+        assert!(matches!(
+            bake_key_frames(&mut mneb_file),
+            Err(BakeError::NoFrames)
+        ));
+        assert_eq!(mneb_file, original);
+    }
+}
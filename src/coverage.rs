@@ -0,0 +1,200 @@
+//! Which bytes of a file were read while parsing it, to help work out what the unknown parts
+//! of the format mean.
+
+use serde::Serialize;
+use std::fmt::Write;
+
+const BYTES_PER_ROW: usize = 16;
+
+/// A range of bytes that a field was read from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub len: usize,
+    /// The field that was read, such as `curves[0].control_points[1].x`.
+    pub label: String,
+}
+
+/// What a range of unread bytes seems to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapKind {
+    /// Zeroes between fields, most likely for alignment.
+    Padding,
+    /// Data between fields that nothing points to.
+    Unknown,
+    /// Bytes after the last field that was read.
+    Tail,
+}
+
+/// A range of bytes that was never read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Gap {
+    pub start: usize,
+    pub len: usize,
+    pub kind: GapKind,
+}
+
+/// Finds every range of `raw` that isn't covered by `spans`.
+pub fn gaps(raw: &[u8], spans: &[Span]) -> Vec<Gap> {
+    let mut read = vec![false; raw.len()];
+    for span in spans {
+        let end = (span.start + span.len).min(raw.len());
+        if span.start < end {
+            read[span.start..end].fill(true);
+        }
+    }
+
+    let last_read = read.iter().rposition(|&r| r).map_or(0, |i| i + 1);
+
+    let mut gaps = Vec::new();
+    let mut i = 0;
+    while i < raw.len() {
+        if read[i] {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < raw.len() && !read[i] {
+            i += 1;
+        }
+
+        let kind = if start >= last_read {
+            GapKind::Tail
+        } else if raw[start..i].iter().all(|&b| b == 0) {
+            GapKind::Padding
+        } else {
+            GapKind::Unknown
+        };
+
+        gaps.push(Gap {
+            start,
+            len: i - start,
+            kind,
+        });
+    }
+
+    gaps
+}
+
+/// Formats `raw` as a hex dump with one entry per span or gap, in file order. Each entry starts
+/// on a new row, labeled with its field.
+pub fn hex_dump(raw: &[u8], spans: &[Span]) -> String {
+    let mut entries: Vec<(usize, usize, String)> = spans
+        .iter()
+        .map(|span| (span.start, span.len, span.label.clone()))
+        .collect();
+
+    entries.extend(gaps(raw, spans).into_iter().map(|gap| {
+        let kind = match gap.kind {
+            GapKind::Padding => "padding",
+            GapKind::Unknown => "unknown",
+            GapKind::Tail => "tail",
+        };
+        (gap.start, gap.len, format!("<unread {}>", kind))
+    }));
+
+    entries.sort_by_key(|&(start, ..)| start);
+
+    let mut out = String::new();
+    for (start, len, label) in entries {
+        let end = (start + len).min(raw.len());
+        for (row, offset) in (start..end).step_by(BYTES_PER_ROW).enumerate() {
+            let bytes = &raw[offset..(offset + BYTES_PER_ROW).min(end)];
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+            let mut line = format!(
+                "{:08X}  {:<width$}",
+                offset,
+                hex.join(" "),
+                width = BYTES_PER_ROW * 3 - 1
+            );
+            if row == 0 {
+                let _ = write!(line, "  {}", label);
+            }
+
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::MNEBFile;
+    use crate::testing::{CURVES, CURVES_ODD, DEMO};
+
+    fn traced_gaps(raw: &[u8]) -> Vec<Gap> {
+        let (result, spans) = MNEBFile::from_bytes_traced(raw);
+        result.unwrap();
+        gaps(raw, &spans)
+    }
+
+    fn gap(start: usize, len: usize, kind: GapKind) -> Gap {
+        Gap { start, len, kind }
+    }
+
+    #[test]
+    fn curves_are_read_completely() {
+        assert!(traced_gaps(CURVES).is_empty());
+    }
+
+    #[test]
+    fn demo_option_values_are_padded() {
+        // "hello" and "ab" are padded to 4 bytes
+        assert_eq!(
+            traced_gaps(DEMO),
+            [
+                gap(0x91, 3, GapKind::Padding),
+                gap(0xAA, 2, GapKind::Padding),
+            ]
+        );
+    }
+
+    #[test]
+    fn unread_data_and_tails() {
+        assert_eq!(
+            traced_gaps(CURVES_ODD),
+            [
+                gap(0x18, 8, GapKind::Unknown),
+                gap(0xFE, 6, GapKind::Unknown),
+                gap(0x158, 4, GapKind::Unknown),
+                gap(0x16C, 4, GapKind::Tail),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_label_fields() {
+        let (_, spans) = MNEBFile::from_bytes_traced(DEMO);
+
+        let value = spans
+            .iter()
+            .find(|span| span.label == "demo_option_sets[0].demo_options[0].value")
+            .unwrap();
+        assert_eq!((value.start, value.len), (0x8C, 5));
+
+        let dump = hex_dump(DEMO, &spans);
+        let line = |offset: &str| dump.lines().find(|line| line.starts_with(offset)).unwrap();
+        assert!(
+            line("0000008C  68 65 6C 6C 6F ")
+                .ends_with("  demo_option_sets[0].demo_options[0].value")
+        );
+        assert!(line("00000091  00 00 00 ").ends_with("  <unread padding>"));
+    }
+
+    #[test]
+    fn spans_past_the_end_are_ignored() {
+        let spans = [Span {
+            start: 2,
+            len: 10,
+            label: "field".to_string(),
+        }];
+
+        assert_eq!(gaps(&[1, 0, 0, 0], &spans), [gap(0, 2, GapKind::Unknown)]);
+    }
+}
@@ -0,0 +1,703 @@
+//! Structural comparison of two files.
+//!
+//! Curves are matched by name, key frame sets by the node they move, key frames by their frame,
+//! and demo option sets and options by name. Control points are compared by index.
+//!
+//! Key frames that are left over are then paired up if they hold the same state and position,
+//! which makes them retimed. A key frame that changed both its frame and its position can't
+//! be told apart from one that was removed while another was added, so it's listed as both.
+
+use crate::mneb::{ControlPoint, Curve, DemoOptionSet, KeyFrame, KeyFrameSet, MNEBFile};
+use serde::Serialize;
+use std::fmt;
+
+/// A difference between two files.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    /// A header field changed, including unknown ones.
+    Header {
+        field: &'static str,
+        old: String,
+        new: String,
+    },
+    CurveAdded {
+        curve: String,
+    },
+    CurveRemoved {
+        curve: String,
+    },
+    ControlPointAdded {
+        curve: String,
+        index: usize,
+        point: ControlPoint,
+    },
+    ControlPointRemoved {
+        curve: String,
+        index: usize,
+        point: ControlPoint,
+    },
+    ControlPointChanged {
+        curve: String,
+        index: usize,
+        old: ControlPoint,
+        new: ControlPoint,
+    },
+    KnotsChanged {
+        curve: String,
+        old: Vec<f32>,
+        new: Vec<f32>,
+    },
+    KeyFrameSetAdded {
+        curve: String,
+        node_index: u16,
+    },
+    KeyFrameSetRemoved {
+        curve: String,
+        node_index: u16,
+    },
+    KeyFrameAdded {
+        curve: String,
+        node_index: u16,
+        key_frame: KeyFrame,
+    },
+    KeyFrameRemoved {
+        curve: String,
+        node_index: u16,
+        key_frame: KeyFrame,
+    },
+    /// A key frame at the same frame moved, or was switched on or off.
+    KeyFrameMoved {
+        curve: String,
+        node_index: u16,
+        old: KeyFrame,
+        new: KeyFrame,
+    },
+    /// A key frame was moved to another frame, keeping its state and position.
+    KeyFrameRetimed {
+        curve: String,
+        node_index: u16,
+        old: KeyFrame,
+        new: KeyFrame,
+    },
+    /// An unknown field of a curve or demo option set changed.
+    UnknownChanged {
+        owner: String,
+        field: &'static str,
+        old: String,
+        new: String,
+    },
+    DemoOptionSetAdded {
+        set: String,
+    },
+    DemoOptionSetRemoved {
+        set: String,
+    },
+    DemoOptionAdded {
+        set: String,
+        option: String,
+        value: String,
+    },
+    DemoOptionRemoved {
+        set: String,
+        option: String,
+        value: String,
+    },
+    DemoOptionChanged {
+        set: String,
+        option: String,
+        old: String,
+        new: String,
+    },
+}
+
+fn point(p: &ControlPoint) -> String {
+    format!("({}, {}, {}) texture {}", p.x, p.y, p.z, p.texture_index)
+}
+
+fn key(k: &KeyFrame) -> String {
+    format!(
+        "frame {} at ({}, {}){}",
+        k.frame,
+        k.x,
+        k.y,
+        if k.is_active { "" } else { ", inactive" }
+    )
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Header { field, old, new } => write!(f, "header: {field}: {old} -> {new}"),
+            Self::CurveAdded { curve } => write!(f, "+ curve {curve}"),
+            Self::CurveRemoved { curve } => write!(f, "- curve {curve}"),
+            Self::ControlPointAdded {
+                curve,
+                index,
+                point: p,
+            } => write!(f, "{curve}: + control point {index} {}", point(p)),
+            Self::ControlPointRemoved {
+                curve,
+                index,
+                point: p,
+            } => write!(f, "{curve}: - control point {index} {}", point(p)),
+            Self::ControlPointChanged {
+                curve,
+                index,
+                old,
+                new,
+            } => write!(
+                f,
+                "{curve}: control point {index}: {} -> {}",
+                point(old),
+                point(new)
+            ),
+            Self::KnotsChanged { curve, old, new } => {
+                write!(f, "{curve}: knots: {old:?} -> {new:?}")
+            }
+            Self::KeyFrameSetAdded { curve, node_index } => {
+                write!(f, "{curve}: + key frame set for node {node_index}")
+            }
+            Self::KeyFrameSetRemoved { curve, node_index } => {
+                write!(f, "{curve}: - key frame set for node {node_index}")
+            }
+            Self::KeyFrameAdded {
+                curve,
+                node_index,
+                key_frame,
+            } => write!(f, "{curve}: node {node_index}: + {}", key(key_frame)),
+            Self::KeyFrameRemoved {
+                curve,
+                node_index,
+                key_frame,
+            } => write!(f, "{curve}: node {node_index}: - {}", key(key_frame)),
+            Self::KeyFrameMoved {
+                curve,
+                node_index,
+                old,
+                new,
+            } => write!(
+                f,
+                "{curve}: node {node_index}: {} -> {}",
+                key(old),
+                key(new)
+            ),
+            Self::KeyFrameRetimed {
+                curve,
+                node_index,
+                old,
+                new,
+            } => write!(
+                f,
+                "{curve}: node {node_index}: {} -> frame {}",
+                key(old),
+                new.frame
+            ),
+            Self::UnknownChanged {
+                owner,
+                field,
+                old,
+                new,
+            } => write!(f, "{owner}: {field}: {old} -> {new}"),
+            Self::DemoOptionSetAdded { set } => write!(f, "+ demo option set {set}"),
+            Self::DemoOptionSetRemoved { set } => write!(f, "- demo option set {set}"),
+            Self::DemoOptionAdded { set, option, value } => {
+                write!(f, "{set}: + {option} = {value:?}")
+            }
+            Self::DemoOptionRemoved { set, option, value } => {
+                write!(f, "{set}: - {option} = {value:?}")
+            }
+            Self::DemoOptionChanged {
+                set,
+                option,
+                old,
+                new,
+            } => write!(f, "{set}: {option}: {old:?} -> {new:?}"),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Pairs up items with the same name, in order. Returns the pairs, followed by the indices of
+/// the old items and new items that have no match.
+fn match_by_name<T>(
+    old: &[T],
+    new: &[T],
+    name: impl Fn(&T) -> &str,
+) -> (Vec<(usize, usize)>, Vec<usize>, Vec<usize>) {
+    let mut matched = vec![false; old.len()];
+    let mut pairs = Vec::new();
+    let mut added = Vec::new();
+
+    for (j, item) in new.iter().enumerate() {
+        match (0..old.len()).find(|&i| !matched[i] && name(&old[i]) == name(item)) {
+            Some(i) => {
+                matched[i] = true;
+                pairs.push((i, j));
+            }
+            None => added.push(j),
+        }
+    }
+
+    let removed = (0..old.len()).filter(|&i| !matched[i]).collect();
+    (pairs, removed, added)
+}
+
+fn diff_key_frame_sets(curve: &str, old: &KeyFrameSet, new: &KeyFrameSet, out: &mut Vec<Change>) {
+    let node_index = new.node_index;
+    let mut matched = vec![false; old.key_frames.len()];
+
+    // the old key frame at the same frame as each new one, if there is one
+    let same_frame: Vec<Option<usize>> = new
+        .key_frames
+        .iter()
+        .map(|key_frame| {
+            let found = (0..old.key_frames.len())
+                .find(|&i| !matched[i] && old.key_frames[i].frame == key_frame.frame);
+            if let Some(i) = found {
+                matched[i] = true;
+            }
+            found
+        })
+        .collect();
+
+    // then a left over old key frame in the same state and position, if the frame changed
+    let retimed: Vec<Option<usize>> = new
+        .key_frames
+        .iter()
+        .zip(&same_frame)
+        .map(|(key_frame, found)| {
+            if found.is_some() {
+                return None;
+            }
+
+            let found = (0..old.key_frames.len()).find(|&i| {
+                let other = &old.key_frames[i];
+                !matched[i]
+                    && other.is_active == key_frame.is_active
+                    && (!key_frame.is_active || (other.x, other.y) == (key_frame.x, key_frame.y))
+            });
+            if let Some(i) = found {
+                matched[i] = true;
+            }
+            found
+        })
+        .collect();
+
+    for ((key_frame, same_frame), retimed) in new.key_frames.iter().zip(same_frame).zip(retimed) {
+        match (same_frame, retimed) {
+            (Some(i), _) => {
+                if &old.key_frames[i] != key_frame {
+                    out.push(Change::KeyFrameMoved {
+                        curve: curve.to_string(),
+                        node_index,
+                        old: old.key_frames[i].clone(),
+                        new: key_frame.clone(),
+                    });
+                }
+            }
+            (None, Some(i)) => out.push(Change::KeyFrameRetimed {
+                curve: curve.to_string(),
+                node_index,
+                old: old.key_frames[i].clone(),
+                new: key_frame.clone(),
+            }),
+            (None, None) => out.push(Change::KeyFrameAdded {
+                curve: curve.to_string(),
+                node_index,
+                key_frame: key_frame.clone(),
+            }),
+        }
+    }
+
+    for (i, key_frame) in old.key_frames.iter().enumerate() {
+        if !matched[i] {
+            out.push(Change::KeyFrameRemoved {
+                curve: curve.to_string(),
+                node_index,
+                key_frame: key_frame.clone(),
+            });
+        }
+    }
+}
+
+fn diff_curves(old: &Curve, new: &Curve, out: &mut Vec<Change>) {
+    let curve = || new.name.clone();
+
+    // control points
+    for (index, (a, b)) in old
+        .control_points
+        .iter()
+        .zip(&new.control_points)
+        .enumerate()
+    {
+        if a != b {
+            out.push(Change::ControlPointChanged {
+                curve: curve(),
+                index,
+                old: a.clone(),
+                new: b.clone(),
+            });
+        }
+    }
+
+    for (index, point) in new
+        .control_points
+        .iter()
+        .enumerate()
+        .skip(old.control_points.len())
+    {
+        out.push(Change::ControlPointAdded {
+            curve: curve(),
+            index,
+            point: point.clone(),
+        });
+    }
+
+    for (index, point) in old
+        .control_points
+        .iter()
+        .enumerate()
+        .skip(new.control_points.len())
+    {
+        out.push(Change::ControlPointRemoved {
+            curve: curve(),
+            index,
+            point: point.clone(),
+        });
+    }
+
+    if old.knots != new.knots {
+        out.push(Change::KnotsChanged {
+            curve: curve(),
+            old: old.knots.clone(),
+            new: new.knots.clone(),
+        });
+    }
+
+    // key frames, with sets matched by node
+    let mut matched = vec![false; old.key_frame_sets.len()];
+    for set in &new.key_frame_sets {
+        let found = (0..old.key_frame_sets.len())
+            .find(|&i| !matched[i] && old.key_frame_sets[i].node_index == set.node_index);
+
+        match found {
+            Some(i) => {
+                matched[i] = true;
+                diff_key_frame_sets(&new.name, &old.key_frame_sets[i], set, out);
+            }
+            None => out.push(Change::KeyFrameSetAdded {
+                curve: curve(),
+                node_index: set.node_index,
+            }),
+        }
+    }
+
+    for (i, set) in old.key_frame_sets.iter().enumerate() {
+        if !matched[i] {
+            out.push(Change::KeyFrameSetRemoved {
+                curve: curve(),
+                node_index: set.node_index,
+            });
+        }
+    }
+
+    // unknown fields
+    let unknowns: [(&'static str, String, String); 7] = [
+        ("unk_28", hex(&old.unk_28), hex(&new.unk_28)),
+        ("unk_8c", old.unk_8c.to_string(), new.unk_8c.to_string()),
+        (
+            "unk_90",
+            format!("{:#X}", old.unk_90),
+            format!("{:#X}", new.unk_90),
+        ),
+        (
+            "unk_94",
+            format!("{:#X}", old.unk_94),
+            format!("{:#X}", new.unk_94),
+        ),
+        (
+            "unk_98",
+            format!("{:#X}", old.unk_98),
+            format!("{:#X}", new.unk_98),
+        ),
+        (
+            "unk_a8",
+            format!("{:?}", old.unk_a8),
+            format!("{:?}", new.unk_a8),
+        ),
+        ("unk_b8", hex(&old.unk_b8), hex(&new.unk_b8)),
+    ];
+
+    for (field, a, b) in unknowns {
+        if a != b {
+            out.push(Change::UnknownChanged {
+                owner: curve(),
+                field,
+                old: a,
+                new: b,
+            });
+        }
+    }
+}
+
+fn diff_demo_option_sets(old: &DemoOptionSet, new: &DemoOptionSet, out: &mut Vec<Change>) {
+    let set = || new.name.clone();
+    let (pairs, removed, added) = match_by_name(&old.demo_options, &new.demo_options, |o| &o.name);
+
+    for (i, j) in pairs {
+        let (a, b) = (&old.demo_options[i], &new.demo_options[j]);
+        if a.value != b.value {
+            out.push(Change::DemoOptionChanged {
+                set: set(),
+                option: b.name.clone(),
+                old: a.value.clone(),
+                new: b.value.clone(),
+            });
+        }
+    }
+
+    for j in added {
+        out.push(Change::DemoOptionAdded {
+            set: set(),
+            option: new.demo_options[j].name.clone(),
+            value: new.demo_options[j].value.clone(),
+        });
+    }
+
+    for i in removed {
+        out.push(Change::DemoOptionRemoved {
+            set: set(),
+            option: old.demo_options[i].name.clone(),
+            value: old.demo_options[i].value.clone(),
+        });
+    }
+
+    if old.unk_20 != new.unk_20 {
+        out.push(Change::UnknownChanged {
+            owner: set(),
+            field: "unk_20",
+            old: hex(&old.unk_20),
+            new: hex(&new.unk_20),
+        });
+    }
+}
+
+/// Lists every difference between `old` and `new`. An empty list means they hold the same
+/// data.
+pub fn diff(old: &MNEBFile, new: &MNEBFile) -> Vec<Change> {
+    let mut out = Vec::new();
+
+    let header: [(&'static str, String, String); 5] = [
+        (
+            "endian",
+            format!("{:?}", old.endian),
+            format!("{:?}", new.endian),
+        ),
+        (
+            "lock",
+            format!("{:#X}", old.lock),
+            format!("{:#X}", new.lock),
+        ),
+        (
+            "frame_count",
+            old.frame_count.to_string(),
+            new.frame_count.to_string(),
+        ),
+        (
+            "is_looped",
+            old.is_looped.to_string(),
+            new.is_looped.to_string(),
+        ),
+        (
+            "unk_8",
+            format!("{:#X}", old._unk_8),
+            format!("{:#X}", new._unk_8),
+        ),
+    ];
+
+    for (field, a, b) in header {
+        if a != b {
+            out.push(Change::Header {
+                field,
+                old: a,
+                new: b,
+            });
+        }
+    }
+
+    let (pairs, removed, added) = match_by_name(&old.curves, &new.curves, |c| &c.name);
+    for i in removed {
+        out.push(Change::CurveRemoved {
+            curve: old.curves[i].name.clone(),
+        });
+    }
+    for j in added {
+        out.push(Change::CurveAdded {
+            curve: new.curves[j].name.clone(),
+        });
+    }
+    for (i, j) in pairs {
+        diff_curves(&old.curves[i], &new.curves[j], &mut out);
+    }
+
+    let (pairs, removed, added) =
+        match_by_name(&old.demo_option_sets, &new.demo_option_sets, |s| &s.name);
+    for i in removed {
+        out.push(Change::DemoOptionSetRemoved {
+            set: old.demo_option_sets[i].name.clone(),
+        });
+    }
+    for j in added {
+        out.push(Change::DemoOptionSetAdded {
+            set: new.demo_option_sets[j].name.clone(),
+        });
+    }
+    for (i, j) in pairs {
+        diff_demo_option_sets(&old.demo_option_sets[i], &new.demo_option_sets[j], &mut out);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{curves, one_curve};
+
+    fn key_frame_changes(old: Vec<KeyFrame>, new: Vec<KeyFrame>) -> Vec<Change> {
+        diff(&one_curve(old, 60), &one_curve(new, 60))
+    }
+
+    #[test]
+    fn same_data() {
+        assert!(diff(&curves(), &curves()).is_empty());
+    }
+
+    #[test]
+    fn added_and_removed_curves() {
+        let old = curves();
+        let mut new = old.clone();
+        new.curves[0].name = "renamed".to_string();
+
+        assert_eq!(
+            diff(&old, &new),
+            [
+                Change::CurveRemoved {
+                    curve: old.curves[0].name.clone(),
+                },
+                Change::CurveAdded {
+                    curve: "renamed".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_control_points() {
+        let old = curves();
+        let mut new = old.clone();
+        let curve = &mut new.curves[0];
+        curve.control_points[1].x += 5;
+        let removed = curve.control_points.pop().unwrap();
+
+        let name = curve.name.clone();
+        assert_eq!(
+            diff(&old, &new),
+            [
+                Change::ControlPointChanged {
+                    curve: name.clone(),
+                    index: 1,
+                    old: old.curves[0].control_points[1].clone(),
+                    new: new.curves[0].control_points[1].clone(),
+                },
+                Change::ControlPointRemoved {
+                    curve: name,
+                    index: old.curves[0].control_points.len() - 1,
+                    point: removed,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_key_frames() {
+        let changes = key_frame_changes(
+            vec![
+                KeyFrame::new(0, true, 0, 0),
+                KeyFrame::new(10, true, 5, 5),
+                KeyFrame::new(20, false, 0, 0),
+            ],
+            vec![
+                KeyFrame::new(0, true, 1, 0),
+                KeyFrame::new(10, false, 0, 0),
+                KeyFrame::new(30, true, 9, 9),
+            ],
+        );
+
+        assert_eq!(
+            changes,
+            [
+                Change::KeyFrameMoved {
+                    curve: "a".to_string(),
+                    node_index: 1,
+                    old: KeyFrame::new(0, true, 0, 0),
+                    new: KeyFrame::new(0, true, 1, 0),
+                },
+                Change::KeyFrameMoved {
+                    curve: "a".to_string(),
+                    node_index: 1,
+                    old: KeyFrame::new(10, true, 5, 5),
+                    new: KeyFrame::new(10, false, 0, 0),
+                },
+                Change::KeyFrameAdded {
+                    curve: "a".to_string(),
+                    node_index: 1,
+                    key_frame: KeyFrame::new(30, true, 9, 9),
+                },
+                Change::KeyFrameRemoved {
+                    curve: "a".to_string(),
+                    node_index: 1,
+                    key_frame: KeyFrame::new(20, false, 0, 0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn retimed_key_frames() {
+        let changes = key_frame_changes(
+            vec![KeyFrame::new(0, true, 0, 0), KeyFrame::new(10, true, 5, 5)],
+            vec![KeyFrame::new(0, true, 0, 0), KeyFrame::new(25, true, 5, 5)],
+        );
+
+        assert_eq!(
+            changes,
+            [Change::KeyFrameRetimed {
+                curve: "a".to_string(),
+                node_index: 1,
+                old: KeyFrame::new(10, true, 5, 5),
+                new: KeyFrame::new(25, true, 5, 5),
+            }]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            "a: node 1: frame 10 at (5, 5) -> frame 25"
+        );
+    }
+
+    #[test]
+    fn added_and_removed_key_frame_sets() {
+        let changes = key_frame_changes(Vec::new(), vec![KeyFrame::new(0, true, 0, 0)]);
+        assert_eq!(
+            changes,
+            [Change::KeyFrameSetAdded {
+                curve: "a".to_string(),
+                node_index: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::string::FromUtf8Error;
+use thiserror::Error;
+
+/// An error encountered while reading or writing an MNEB file.
+///
+/// Parsing errors carry the byte offset they occurred at and the struct that was being parsed.
+#[derive(Debug, Error)]
+pub enum MnebError {
+    #[error(
+        "Truncated {context} at offset {offset:#X}: needed {needed:#X} bytes, but only {available:#X} are left."
+    )]
+    TruncatedBlock {
+        context: &'static str,
+        offset: u64,
+        needed: u64,
+        available: u64,
+    },
+
+    #[error(
+        "Table in {context} at offset {offset:#X} brings the size of every table read to {total:#X} bytes, more than the whole file, so some of them overlap."
+    )]
+    OverlappingTables {
+        context: &'static str,
+        offset: u64,
+        total: u64,
+    },
+
+    #[error(
+        "Invalid {context} magic at offset {offset:#X}: expected {}, found {found:02X?}.",
+        String::from_utf8_lossy(expected)
+    )]
+    BadMagic {
+        context: &'static str,
+        offset: u64,
+        expected: [u8; 4],
+        found: [u8; 4],
+    },
+
+    #[error("Offset in {context} at {offset:#X} points to {target:#X}, which is out of range.")]
+    OffsetOutOfRange {
+        context: &'static str,
+        offset: u64,
+        target: u64,
+    },
+
+    #[error("Invalid string in {context} at offset {offset:#X}: {source}")]
+    BadString {
+        context: &'static str,
+        offset: u64,
+        source: FromUtf8Error,
+    },
+
+    #[error("Name \"{name}\" in {context} does not fit in {size:#X} bytes.")]
+    NameTooLong {
+        context: &'static str,
+        name: String,
+        size: usize,
+    },
+
+    #[error("{context} has {count} entries, which is more than the format can store.")]
+    TooManyEntries { context: &'static str, count: usize },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// An error encountered while importing curves from an SVG file.
+#[cfg(feature = "svg")]
+#[derive(Debug, Error)]
+pub enum SvgError {
+    #[error("Invalid SVG: {0}")]
+    Xml(#[from] roxmltree::Error),
+
+    #[error("Invalid {attribute} on \"{id}\": {source}")]
+    BadAttribute {
+        id: String,
+        attribute: &'static str,
+        source: svgtypes::Error,
+    },
+
+    #[error("Path \"{id}\" has {count} points, which is more than a curve can store.")]
+    TooManyPoints { id: String, count: usize },
+
+    #[error("Coordinate {value} in \"{id}\" does not fit in an i16.")]
+    Overflow { id: String, value: f64 },
+
+    #[error("Invalid animation of \"{id}\": {reason}")]
+    BadAnimation { id: String, reason: String },
+}
+
+/// An error encountered while importing key frames from CSV.
+#[cfg(feature = "csv")]
+#[derive(Debug, Error)]
+pub enum CsvError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    #[error("Line {line}: there is no curve called \"{curve}\".")]
+    UnknownCurve { line: u64, curve: String },
+
+    #[error(
+        "Line {line}: node {node_index} is out of range, since \"{curve}\" has {count} control points."
+    )]
+    NodeOutOfRange {
+        line: u64,
+        curve: String,
+        node_index: u16,
+        count: usize,
+    },
+}
+
+/// An error encountered while moving curves between files.
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("There is no curve called \"{name}\".")]
+    CurveNotFound { name: String },
+
+    #[error("More than one file has a curve called \"{name}\".")]
+    NameCollision { name: String },
+
+    #[error("The files have different {field} values: {first} and {other}.")]
+    HeaderMismatch {
+        field: &'static str,
+        first: String,
+        other: String,
+    },
+
+    #[error("There are no files with curves to merge.")]
+    NoCurves,
+}
+
+/// An error encountered while transforming curves.
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error("There is no curve called \"{name}\".")]
+    CurveNotFound { name: String },
+
+    #[error("The {item} of curve \"{curve}\" would move to {value}, which doesn't fit in an i16.")]
+    Overflow {
+        curve: String,
+        item: String,
+        value: f32,
+    },
+}
+
+/// An error encountered while retiming key frames.
+#[derive(Debug, Error)]
+pub enum RetimeError {
+    #[error("The scale must be positive, but it's {0}.")]
+    BadScale(f32),
+
+    #[error(
+        "The range from frame {start} to {end} is empty or past the frame count of {frame_count}."
+    )]
+    BadRange {
+        start: u16,
+        end: u16,
+        frame_count: u16,
+    },
+
+    #[error("Frame {frame} would be retimed to {value}, which doesn't fit in a u16.")]
+    FrameOutOfRange { frame: u16, value: f32 },
+
+    #[error("{curve}: node {node_index}: more than one key frame would land on frame {frame}.")]
+    Collision {
+        curve: String,
+        node_index: u16,
+        frame: u16,
+    },
+}
+
+/// An error encountered while baking key frames.
+#[derive(Debug, Error)]
+pub enum BakeError {
+    #[error("The frame count is 0, so there are no frames to bake.")]
+    NoFrames,
+
+    #[error(
+        "Curve \"{curve}\" has {count} control points, which is more than key frames can address."
+    )]
+    TooManyPoints { curve: String, count: usize },
+}
@@ -0,0 +1,460 @@
+//! Exporting curves as glTF 2.0, so they can be inspected in 3D tools such as Blender.
+//!
+//! Each curve becomes a node with one child per control point, and each key frame set becomes
+//! a translation channel on the node of the control point it animates. Everything sits under a
+//! root node that flips the y axis, since y points down in MNEB files.
+
+use crate::mneb::{Curve, KeyFrame, MNEBFile};
+use base64::Engine;
+use byteorder::{LittleEndian, WriteBytesExt};
+use serde_json::{Value, json};
+use std::io::{self, Write};
+
+/// How long before an abrupt change, in seconds, the previous position is repeated. glTF has
+/// no way to jump within a linear channel, so a jump becomes a very short move instead.
+const JUMP_TIME: f32 = 0.001;
+
+const GLB_MAGIC: u32 = 0x46546C67; // "glTF"
+const GLB_JSON_CHUNK: u32 = 0x4E4F534A; // "JSON"
+const GLB_BIN_CHUNK: u32 = 0x004E4942; // "BIN\0"
+
+const FLOAT: u32 = 5126;
+
+/// How to export a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfOptions {
+    /// The speed of the animation, in frames per second.
+    pub framerate: f32,
+    /// How many glTF units, usually meters, one MNEB unit spans.
+    pub scale: f32,
+}
+
+/// A JSON document and the binary buffer it refers to.
+struct Document {
+    json: Value,
+    buffer: Vec<u8>,
+}
+
+/// Collects accessors, each with its own buffer view, into a single buffer.
+#[derive(Default)]
+struct Accessors {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Accessors {
+    /// Adds a list of floats, grouped by `kind`, such as `"SCALAR"` or `"VEC3"`. Returns the
+    /// index of the new accessor.
+    fn push(&mut self, kind: &str, components: usize, values: &[f32]) -> usize {
+        let offset = self.buffer.len();
+        for &value in values {
+            // writing to a Vec can't fail
+            let _ = self.buffer.write_f32::<LittleEndian>(value);
+        }
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": values.len() * 4,
+        }));
+
+        let mut min = vec![f32::MAX; components];
+        let mut max = vec![f32::MIN; components];
+        for chunk in values.chunks(components) {
+            for (i, &value) in chunk.iter().enumerate() {
+                min[i] = min[i].min(value);
+                max[i] = max[i].max(value);
+            }
+        }
+
+        self.accessors.push(json!({
+            "bufferView": self.buffer_views.len() - 1,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": kind,
+            "min": min,
+            "max": max,
+        }));
+
+        self.accessors.len() - 1
+    }
+}
+
+/// The times, in frames, and positions that reproduce the key frames of a node with linear
+/// interpolation, following the same rules as [`interpolate`](crate::animation::interpolate).
+/// Times are strictly increasing, as glTF requires.
+fn samples(key_frames: &[KeyFrame], rest: [f32; 3], jump: f32) -> Vec<(f32, [f32; 3])> {
+    let position = |k: &KeyFrame| {
+        if k.is_active {
+            [k.x as f32, k.y as f32, rest[2]]
+        } else {
+            rest
+        }
+    };
+
+    let mut samples: Vec<(f32, [f32; 3])> = Vec::new();
+    let mut push = |time: f32, position: [f32; 3]| {
+        if samples.last().is_none_or(|&(last, _)| time > last) {
+            samples.push((time, position));
+        }
+    };
+
+    let (Some(first), Some(last)) = (key_frames.first(), key_frames.last()) else {
+        return Vec::new();
+    };
+
+    // before the first key frame, the last one applies
+    let first_frame = first.frame as f32;
+    if first_frame > 0.0 && key_frames.len() > 1 {
+        push(0.0, position(last));
+        push(first_frame - jump.min(first_frame / 2.0), position(last));
+    }
+
+    for (i, key_frame) in key_frames.iter().enumerate() {
+        push(key_frame.frame as f32, position(key_frame));
+
+        // a hold, which ends with a jump to the next key frame
+        if let Some(next) = key_frames.get(i + 1)
+            && !(key_frame.is_active && next.is_active)
+            && position(key_frame) != position(next)
+        {
+            let gap = next.frame as f32 - key_frame.frame as f32;
+            if gap > 0.0 {
+                push(next.frame as f32 - jump.min(gap / 2.0), position(key_frame));
+            }
+        }
+    }
+
+    samples
+}
+
+/// Adds a curve's nodes to `nodes` and its channels to `channels` and `samplers`. Returns the
+/// index of the curve's node.
+fn add_curve(
+    curve: &Curve,
+    options: &GltfOptions,
+    accessors: &mut Accessors,
+    nodes: &mut Vec<Value>,
+    channels: &mut Vec<Value>,
+    samplers: &mut Vec<Value>,
+) -> usize {
+    let first_child = nodes.len() + 1;
+    let children: Vec<usize> = (first_child..first_child + curve.control_points.len()).collect();
+
+    nodes.push(json!({ "name": curve.name }));
+    if !children.is_empty() {
+        nodes[first_child - 1]["children"] = json!(children);
+    }
+
+    for (i, point) in curve.control_points.iter().enumerate() {
+        nodes.push(json!({
+            "name": format!("{}_{}", curve.name, i),
+            "translation": [point.x as f32, point.y as f32, point.z as f32],
+            "extras": { "texture_index": point.texture_index },
+        }));
+    }
+
+    let mut animated = vec![false; curve.control_points.len()];
+    let jump = JUMP_TIME * options.framerate;
+
+    for key_set in &curve.key_frame_sets {
+        let idx = key_set.node_index as usize;
+        let Some(point) = curve.control_points.get(idx) else {
+            continue;
+        };
+
+        // a node can only have one translation channel
+        if animated[idx] {
+            continue;
+        }
+
+        let rest = [point.x as f32, point.y as f32, point.z as f32];
+        let samples = samples(&key_set.key_frames, rest, jump);
+        if samples.is_empty() {
+            continue;
+        }
+
+        animated[idx] = true;
+
+        let times: Vec<f32> = samples
+            .iter()
+            .map(|&(frame, _)| frame / options.framerate)
+            .collect();
+        let positions: Vec<f32> = samples.iter().flat_map(|&(_, p)| p).collect();
+
+        let input = accessors.push("SCALAR", 1, &times);
+        let output = accessors.push("VEC3", 3, &positions);
+
+        samplers.push(json!({
+            "input": input,
+            "output": output,
+            "interpolation": "LINEAR",
+        }));
+        channels.push(json!({
+            "sampler": samplers.len() - 1,
+            "target": { "node": children[idx], "path": "translation" },
+        }));
+    }
+
+    first_child - 1
+}
+
+fn build(mneb_file: &MNEBFile, name: &str, options: &GltfOptions) -> Document {
+    let mut accessors = Accessors::default();
+    let mut nodes = vec![json!({
+        "name": name,
+        "scale": [options.scale, -options.scale, options.scale],
+    })];
+    let mut channels = Vec::new();
+    let mut samplers = Vec::new();
+
+    let mut curve_nodes = Vec::new();
+    for curve in &mneb_file.curves {
+        curve_nodes.push(add_curve(
+            curve,
+            options,
+            &mut accessors,
+            &mut nodes,
+            &mut channels,
+            &mut samplers,
+        ));
+    }
+
+    if !curve_nodes.is_empty() {
+        nodes[0]["children"] = json!(curve_nodes);
+    }
+
+    let mut json = json!({
+        "asset": { "version": "2.0", "generator": "mneb-tool" },
+        "scene": 0,
+        "scenes": [{ "name": name, "nodes": [0] }],
+        "nodes": nodes,
+        "extras": {
+            "frame_count": mneb_file.frame_count,
+            "is_looped": mneb_file.is_looped,
+            "framerate": options.framerate,
+        },
+    });
+
+    if !channels.is_empty() {
+        json["animations"] = json!([{
+            "name": name,
+            "channels": channels,
+            "samplers": samplers,
+        }]);
+        json["accessors"] = json!(accessors.accessors);
+        json["bufferViews"] = json!(accessors.buffer_views);
+        json["buffers"] = json!([{ "byteLength": accessors.buffer.len() }]);
+    }
+
+    Document {
+        json,
+        buffer: accessors.buffer,
+    }
+}
+
+/// Writes every curve of `mneb_file` as a `.gltf` file, with the animation data embedded.
+/// `name` is given to the scene, its root node and the animation.
+pub fn write_gltf<W: Write>(
+    mneb_file: &MNEBFile,
+    name: &str,
+    options: &GltfOptions,
+    writer: W,
+) -> io::Result<()> {
+    let mut document = build(mneb_file, name, options);
+
+    if !document.buffer.is_empty() {
+        document.json["buffers"][0]["uri"] = json!(format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&document.buffer)
+        ));
+    }
+
+    serde_json::to_writer_pretty(writer, &document.json)?;
+    Ok(())
+}
+
+/// Writes every curve of `mneb_file` as a binary `.glb` file. `name` is given to the scene,
+/// its root node and the animation.
+pub fn write_glb<W: Write>(
+    mneb_file: &MNEBFile,
+    name: &str,
+    options: &GltfOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    let document = build(mneb_file, name, options);
+
+    // chunks are padded to 4 bytes, JSON with spaces and binary data with zeroes
+    let mut json = serde_json::to_vec(&document.json)?;
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let mut buffer = document.buffer;
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+    let mut length = 12 + 8 + json.len();
+    if !buffer.is_empty() {
+        length += 8 + buffer.len();
+    }
+
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "GLB files can't exceed 4 GiB");
+
+    writer.write_u32::<LittleEndian>(GLB_MAGIC)?;
+    writer.write_u32::<LittleEndian>(2)?;
+    writer.write_u32::<LittleEndian>(u32::try_from(length).map_err(|_| too_large())?)?;
+
+    writer.write_u32::<LittleEndian>(json.len() as u32)?;
+    writer.write_u32::<LittleEndian>(GLB_JSON_CHUNK)?;
+    writer.write_all(&json)?;
+
+    if !buffer.is_empty() {
+        writer.write_u32::<LittleEndian>(buffer.len() as u32)?;
+        writer.write_u32::<LittleEndian>(GLB_BIN_CHUNK)?;
+        writer.write_all(&buffer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::curves;
+    use byteorder::ByteOrder;
+
+    const OPTIONS: GltfOptions = GltfOptions {
+        framerate: 60.0,
+        scale: 1.0,
+    };
+
+    /// Splits a GLB file into its JSON and binary chunks, checking the layout along the way.
+    fn parse_glb(glb: &[u8]) -> (Value, Vec<u8>) {
+        assert_eq!(LittleEndian::read_u32(&glb[0..]), GLB_MAGIC);
+        assert_eq!(LittleEndian::read_u32(&glb[4..]), 2);
+        assert_eq!(LittleEndian::read_u32(&glb[8..]) as usize, glb.len());
+
+        let json_length = LittleEndian::read_u32(&glb[12..]) as usize;
+        assert_eq!(LittleEndian::read_u32(&glb[16..]), GLB_JSON_CHUNK);
+        assert_eq!(json_length % 4, 0);
+        let json = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+        let rest = &glb[20 + json_length..];
+        if rest.is_empty() {
+            return (json, Vec::new());
+        }
+
+        let bin_length = LittleEndian::read_u32(rest) as usize;
+        assert_eq!(LittleEndian::read_u32(&rest[4..]), GLB_BIN_CHUNK);
+        assert_eq!(bin_length % 4, 0);
+        assert_eq!(rest.len(), 8 + bin_length);
+        (json, rest[8..].to_vec())
+    }
+
+    /// Reads the floats of an accessor.
+    fn floats(json: &Value, buffer: &[u8], accessor: &Value) -> Vec<f32> {
+        let view = &json["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+        let offset = view["byteOffset"].as_u64().unwrap() as usize;
+        let length = view["byteLength"].as_u64().unwrap() as usize;
+        buffer[offset..offset + length]
+            .chunks(4)
+            .map(LittleEndian::read_f32)
+            .collect()
+    }
+
+    #[test]
+    fn glb_layout() {
+        let mneb_file = curves();
+        let mut glb = Vec::new();
+        write_glb(&mneb_file, "test", &OPTIONS, &mut glb).unwrap();
+        let (json, buffer) = parse_glb(&glb);
+
+        assert!(buffer.len() >= json["buffers"][0]["byteLength"].as_u64().unwrap() as usize);
+        assert_eq!(json["extras"]["frame_count"], mneb_file.frame_count);
+
+        // a root, then each curve followed by one node per control point
+        let nodes = json["nodes"].as_array().unwrap();
+        let num_points: usize = mneb_file
+            .curves
+            .iter()
+            .map(|c| c.control_points.len())
+            .sum();
+        assert_eq!(nodes.len(), 1 + mneb_file.curves.len() + num_points);
+
+        let mut index = 1;
+        for curve in &mneb_file.curves {
+            assert_eq!(nodes[index]["name"], curve.name.as_str());
+            let children = nodes[index]["children"].as_array();
+            assert_eq!(children.map_or(0, Vec::len), curve.control_points.len());
+            index += 1 + curve.control_points.len();
+        }
+    }
+
+    #[test]
+    fn sample_times_follow_the_framerate() {
+        let mneb_file = curves();
+        let mut glb = Vec::new();
+        write_glb(&mneb_file, "test", &OPTIONS, &mut glb).unwrap();
+        let (json, buffer) = parse_glb(&glb);
+
+        let end = mneb_file.frame_count as f32 / OPTIONS.framerate;
+        let accessors = json["accessors"].as_array().unwrap();
+        for sampler in json["animations"][0]["samplers"].as_array().unwrap() {
+            let input = &accessors[sampler["input"].as_u64().unwrap() as usize];
+            let times = floats(&json, &buffer, input);
+
+            assert!(times.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(times.iter().all(|&time| (0.0..=end).contains(&time)));
+            assert_eq!(
+                input["max"][0].as_f64().unwrap() as f32,
+                *times.last().unwrap()
+            );
+        }
+
+        // the first key frame set is all active, so its key frames are sampled as they are
+        let key_frames = &mneb_file.curves[0].key_frame_sets[0].key_frames;
+        let input = &accessors[json["animations"][0]["samplers"][0]["input"]
+            .as_u64()
+            .unwrap() as usize];
+        let expected: Vec<f32> = key_frames
+            .iter()
+            .map(|key_frame| key_frame.frame as f32 / OPTIONS.framerate)
+            .collect();
+        assert_eq!(floats(&json, &buffer, input), expected);
+    }
+
+    #[test]
+    fn holds_end_with_a_jump() {
+        let key_frames = [
+            KeyFrame::new(0, true, 10, 10),
+            KeyFrame::new(10, false, 0, 0),
+            KeyFrame::new(20, true, 30, 30),
+        ];
+        let rest = [0.0, 0.0, 5.0];
+
+        assert_eq!(
+            samples(&key_frames, rest, 0.5),
+            [
+                (0.0, [10.0, 10.0, 5.0]),
+                (9.5, [10.0, 10.0, 5.0]),
+                (10.0, rest),
+                (19.5, rest),
+                (20.0, [30.0, 30.0, 5.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_without_animation_have_no_binary_chunk() {
+        let mut mneb_file = curves();
+        for curve in &mut mneb_file.curves {
+            curve.key_frame_sets.clear();
+        }
+
+        let mut glb = Vec::new();
+        write_glb(&mneb_file, "test", &OPTIONS, &mut glb).unwrap();
+        let (json, buffer) = parse_glb(&glb);
+
+        assert!(buffer.is_empty());
+        assert!(json.get("animations").is_none());
+    }
+}
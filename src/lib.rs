@@ -0,0 +1,45 @@
+//! Reading and writing MNEB files, which hold the curves and demo data of Kirby's Epic Yarn.
+//!
+//! ```no_run
+//! let raw = std::fs::read("my_file.mneb").unwrap();
+//! let file = mneb::MNEBFile::from_bytes(&raw).unwrap();
+//!
+//! for curve in &file.curves {
+//!     println!("{}: {} control points", curve.name, curve.control_points.len());
+//! }
+//!
+//! std::fs::write("out.mneb", file.to_bytes().unwrap()).unwrap();
+//! ```
+
+pub mod animation;
+pub mod bake;
+pub mod coverage;
+pub mod diff;
+mod error;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod lint;
+pub mod merge;
+mod mneb;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod retime;
+pub mod simplify;
+pub mod spline;
+pub mod stats;
+#[cfg(feature = "svg")]
+pub mod svg;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "csv")]
+pub mod tracks;
+pub mod transform;
+
+#[cfg(feature = "csv")]
+pub use error::CsvError;
+#[cfg(feature = "svg")]
+pub use error::SvgError;
+pub use error::{BakeError, MergeError, MnebError, RetimeError, TransformError};
+pub use mneb::{
+    ControlPoint, Curve, DemoOption, DemoOptionSet, Endian, KeyFrame, KeyFrameSet, MNEBFile,
+};
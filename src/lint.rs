@@ -0,0 +1,393 @@
+//! Structural checks that go beyond what's needed to parse a file.
+
+use crate::mneb::{
+    CURVE_NAME_SIZE, Curve, DEMO_OPTION_NAME_SIZE, DEMO_OPTION_SET_NAME_SIZE, MNEBFile,
+};
+use serde::Serialize;
+use std::fmt;
+
+/// How serious an [`Issue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The file can't be written back, or the game is likely to trip over it.
+    Error,
+    /// The file is unusual, but can be written back as it is.
+    Warning,
+}
+
+/// A problem found in a file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// A key frame comes before the one preceding it.
+    KeyFramesOutOfOrder {
+        curve: String,
+        node_index: u16,
+        index: usize,
+        frame: u16,
+        previous: u16,
+    },
+    /// A key frame is past the end of the animation.
+    FrameBeyondCount {
+        curve: String,
+        node_index: u16,
+        frame: u16,
+        frame_count: u16,
+    },
+    /// A key frame set moves a node that doesn't exist.
+    NodeOutOfRange {
+        curve: String,
+        node_index: u16,
+        count: usize,
+    },
+    /// More than one key frame set moves the same node.
+    DuplicateKeyFrameSet { curve: String, node_index: u16 },
+    /// A knot is smaller than the one before it.
+    KnotsNotMonotonic { curve: String, index: usize },
+    /// The number of knots doesn't describe a B-spline of at least degree 1.
+    WrongKnotCount {
+        curve: String,
+        knots: usize,
+        control_points: usize,
+    },
+    /// A curve's name doesn't fit in its fixed-size field.
+    CurveNameTooLong {
+        curve: String,
+        length: usize,
+        size: usize,
+    },
+    /// A demo option set's name doesn't fit in its fixed-size field.
+    DemoOptionSetNameTooLong {
+        set: String,
+        length: usize,
+        size: usize,
+    },
+    /// A demo option's name doesn't fit in its fixed-size field.
+    DemoOptionNameTooLong {
+        set: String,
+        option: String,
+        length: usize,
+        size: usize,
+    },
+    /// A curve's name fills its fixed-size field, leaving no terminating zero.
+    CurveNameUnterminated { curve: String, size: usize },
+    /// A demo option set's name fills its fixed-size field, leaving no terminating zero.
+    DemoOptionSetNameUnterminated { set: String, size: usize },
+    /// A demo option's name fills its fixed-size field, leaving no terminating zero.
+    DemoOptionNameUnterminated {
+        set: String,
+        option: String,
+        size: usize,
+    },
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::CurveNameUnterminated { .. }
+            | Self::DemoOptionSetNameUnterminated { .. }
+            | Self::DemoOptionNameUnterminated { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::KeyFramesOutOfOrder {
+                curve,
+                node_index,
+                index,
+                frame,
+                previous,
+            } => write!(
+                f,
+                "{curve}: node {node_index}: key frame {index} is at frame {frame}, before the previous one at {previous}."
+            ),
+            Self::FrameBeyondCount {
+                curve,
+                node_index,
+                frame,
+                frame_count,
+            } => write!(
+                f,
+                "{curve}: node {node_index}: key frame at frame {frame} is past the frame count of {frame_count}."
+            ),
+            Self::NodeOutOfRange {
+                curve,
+                node_index,
+                count,
+            } => write!(
+                f,
+                "{curve}: key frame set for node {node_index}, but there are only {count} control points."
+            ),
+            Self::DuplicateKeyFrameSet { curve, node_index } => {
+                write!(
+                    f,
+                    "{curve}: more than one key frame set for node {node_index}."
+                )
+            }
+            Self::KnotsNotMonotonic { curve, index } => {
+                write!(
+                    f,
+                    "{curve}: knot {index} is smaller than the one before it."
+                )
+            }
+            Self::WrongKnotCount {
+                curve,
+                knots,
+                control_points,
+            } => write!(
+                f,
+                "{curve}: {knots} knots don't describe a B-spline with {control_points} control points."
+            ),
+            Self::CurveNameTooLong {
+                curve,
+                length,
+                size,
+            } => write!(
+                f,
+                "{curve}: name is {length} bytes long, which does not fit in {size:#X} bytes."
+            ),
+            Self::DemoOptionSetNameTooLong { set, length, size } => write!(
+                f,
+                "{set}: name is {length} bytes long, which does not fit in {size:#X} bytes."
+            ),
+            Self::DemoOptionNameTooLong {
+                set,
+                option,
+                length,
+                size,
+            } => write!(
+                f,
+                "{set}: option \"{option}\" is {length} bytes long, which does not fit in {size:#X} bytes."
+            ),
+            Self::CurveNameUnterminated { curve, size } => write!(
+                f,
+                "{curve}: name fills all {size:#X} bytes, leaving no terminating zero."
+            ),
+            Self::DemoOptionSetNameUnterminated { set, size } => write!(
+                f,
+                "{set}: name fills all {size:#X} bytes, leaving no terminating zero."
+            ),
+            Self::DemoOptionNameUnterminated { set, option, size } => write!(
+                f,
+                "{set}: option \"{option}\" fills all {size:#X} bytes, leaving no terminating zero."
+            ),
+        }
+    }
+}
+
+fn validate_curve(curve: &Curve, frame_count: u16, issues: &mut Vec<Issue>) {
+    let name = || curve.name.clone();
+
+    if curve.name.len() > CURVE_NAME_SIZE {
+        issues.push(Issue::CurveNameTooLong {
+            curve: name(),
+            length: curve.name.len(),
+            size: CURVE_NAME_SIZE,
+        });
+    } else if curve.name.len() == CURVE_NAME_SIZE {
+        issues.push(Issue::CurveNameUnterminated {
+            curve: name(),
+            size: CURVE_NAME_SIZE,
+        });
+    }
+
+    if curve.degree().is_none() {
+        issues.push(Issue::WrongKnotCount {
+            curve: name(),
+            knots: curve.knots.len(),
+            control_points: curve.control_points.len(),
+        });
+    }
+
+    for (i, pair) in curve.knots.windows(2).enumerate() {
+        if pair[1] < pair[0] {
+            issues.push(Issue::KnotsNotMonotonic {
+                curve: name(),
+                index: i + 1,
+            });
+        }
+    }
+
+    for (i, key_set) in curve.key_frame_sets.iter().enumerate() {
+        let node_index = key_set.node_index;
+
+        if node_index as usize >= curve.control_points.len() {
+            issues.push(Issue::NodeOutOfRange {
+                curve: name(),
+                node_index,
+                count: curve.control_points.len(),
+            });
+        }
+
+        // only report each duplicate once
+        if curve.key_frame_sets[..i]
+            .iter()
+            .filter(|set| set.node_index == node_index)
+            .count()
+            == 1
+        {
+            issues.push(Issue::DuplicateKeyFrameSet {
+                curve: name(),
+                node_index,
+            });
+        }
+
+        for (j, key_frame) in key_set.key_frames.iter().enumerate() {
+            if key_frame.frame > frame_count {
+                issues.push(Issue::FrameBeyondCount {
+                    curve: name(),
+                    node_index,
+                    frame: key_frame.frame,
+                    frame_count,
+                });
+            }
+
+            if let Some(previous) = j.checked_sub(1).map(|j| &key_set.key_frames[j])
+                && key_frame.frame < previous.frame
+            {
+                issues.push(Issue::KeyFramesOutOfOrder {
+                    curve: name(),
+                    node_index,
+                    index: j,
+                    frame: key_frame.frame,
+                    previous: previous.frame,
+                });
+            }
+        }
+    }
+}
+
+/// Checks `mneb_file` for problems that don't stop it from being parsed, but that the game or
+/// the writer might trip over.
+pub fn validate(mneb_file: &MNEBFile) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for curve in &mneb_file.curves {
+        validate_curve(curve, mneb_file.frame_count, &mut issues);
+    }
+
+    for set in &mneb_file.demo_option_sets {
+        if set.name.len() > DEMO_OPTION_SET_NAME_SIZE {
+            issues.push(Issue::DemoOptionSetNameTooLong {
+                set: set.name.clone(),
+                length: set.name.len(),
+                size: DEMO_OPTION_SET_NAME_SIZE,
+            });
+        } else if set.name.len() == DEMO_OPTION_SET_NAME_SIZE {
+            issues.push(Issue::DemoOptionSetNameUnterminated {
+                set: set.name.clone(),
+                size: DEMO_OPTION_SET_NAME_SIZE,
+            });
+        }
+
+        for option in &set.demo_options {
+            if option.name.len() > DEMO_OPTION_NAME_SIZE {
+                issues.push(Issue::DemoOptionNameTooLong {
+                    set: set.name.clone(),
+                    option: option.name.clone(),
+                    length: option.name.len(),
+                    size: DEMO_OPTION_NAME_SIZE,
+                });
+            } else if option.name.len() == DEMO_OPTION_NAME_SIZE {
+                issues.push(Issue::DemoOptionNameUnterminated {
+                    set: set.name.clone(),
+                    option: option.name.clone(),
+                    size: DEMO_OPTION_NAME_SIZE,
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::{DemoOption, DemoOptionSet};
+
+    fn curve_file(name: &str) -> MNEBFile {
+        MNEBFile::new(vec![Curve::new(name, Vec::new(), Vec::new())], 60, false)
+    }
+
+    fn is_name_issue(issue: &Issue) -> bool {
+        matches!(
+            issue,
+            Issue::CurveNameTooLong { .. }
+                | Issue::DemoOptionSetNameTooLong { .. }
+                | Issue::DemoOptionNameTooLong { .. }
+                | Issue::CurveNameUnterminated { .. }
+                | Issue::DemoOptionSetNameUnterminated { .. }
+                | Issue::DemoOptionNameUnterminated { .. }
+        )
+    }
+
+    #[test]
+    fn parsed_name_without_terminator() {
+        let name = "a".repeat(CURVE_NAME_SIZE);
+        let raw = curve_file(&name).to_bytes().unwrap();
+        let mneb_file = MNEBFile::from_bytes(&raw).unwrap();
+
+        assert_eq!(
+            validate(&mneb_file),
+            [
+                Issue::CurveNameUnterminated {
+                    curve: name,
+                    size: CURVE_NAME_SIZE,
+                },
+                Issue::WrongKnotCount {
+                    curve: "a".repeat(CURVE_NAME_SIZE),
+                    knots: 0,
+                    control_points: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn names_that_fit() {
+        let name = "a".repeat(CURVE_NAME_SIZE - 1);
+        assert!(!validate(&curve_file(&name)).iter().any(is_name_issue));
+    }
+
+    #[test]
+    fn demo_names() {
+        let option = DemoOption::new("o".repeat(DEMO_OPTION_NAME_SIZE + 1), "value");
+        let set = DemoOptionSet::new("s".repeat(DEMO_OPTION_SET_NAME_SIZE), vec![option]);
+        let issues = validate(&MNEBFile::with_demo_option_sets(vec![set]));
+
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(is_name_issue));
+        assert_eq!(issues[0].severity(), Severity::Warning);
+        assert_eq!(issues[1].severity(), Severity::Error);
+        assert!(issues[1].to_string().contains("does not fit"));
+    }
+
+    #[test]
+    fn lint_matches_the_writer() {
+        // names that fill their field are only a warning, since they can be written
+        let full = curve_file(&"a".repeat(CURVE_NAME_SIZE));
+        assert!(full.to_bytes().is_ok());
+        assert!(
+            validate(&full)
+                .iter()
+                .filter(|issue| is_name_issue(issue))
+                .all(|issue| issue.severity() == Severity::Warning)
+        );
+
+        // while names that don't fit are an error, since they can't
+        let long = curve_file(&"a".repeat(CURVE_NAME_SIZE + 1));
+        assert!(long.to_bytes().is_err());
+        assert!(
+            validate(&long)
+                .iter()
+                .any(|issue| matches!(issue, Issue::CurveNameTooLong { .. })
+                    && issue.severity() == Severity::Error)
+        );
+    }
+}
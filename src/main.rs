@@ -1,16 +1,39 @@
+#[cfg(feature = "viewer")]
 mod animate;
-mod mneb;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use glob::glob;
-use std::{fs, path::Path};
+use mneb::bake;
+use mneb::coverage;
+use mneb::diff;
+use mneb::gltf;
+use mneb::lint;
+use mneb::merge::{self, Collision, HeaderPolicy};
+use mneb::render::{self, Camera, RenderOptions};
+use mneb::retime::{self, FrameCollision, RetimeOptions};
+use mneb::simplify;
+use mneb::stats;
+use mneb::svg;
+use mneb::tracks;
+use mneb::transform::{self, Transform};
+use std::fs;
 
 #[derive(Subcommand, Debug)]
 enum Usage {
+    #[cfg(feature = "viewer")]
     Animate {
         filename: String,
         #[arg(short, long, default_value_t = 60.0f32)]
         framerate: f32,
+        /// Draw the control polygon over the curves. Can be toggled with C.
+        #[arg(short, long)]
+        control_polygon: bool,
+        /// The first frame of a range to loop. Can be set with I.
+        #[arg(long = "in")]
+        in_frame: Option<u16>,
+        /// The frame to loop back at. Can be set with O.
+        #[arg(long = "out")]
+        out_frame: Option<u16>,
     },
     Convert {
         filename: String,
@@ -24,6 +47,324 @@ enum Usage {
         #[arg(short, long)]
         pretty: bool,
     },
+    Import {
+        filename: String,
+        /// The name of the MNEB file to output.
+        #[arg(default_value_t = String::from("out.mneb"))]
+        output_mneb: String,
+        #[arg(short, long, default_value_t = String::from("out"))]
+        /// If processing multiple files, the folder to output the files to.
+        output_folder_name: String,
+    },
+    /// Build an MNEB file from the paths of an SVG file.
+    ImportSvg {
+        filename: String,
+        /// The name of the MNEB file to output.
+        #[arg(default_value_t = String::from("out.mneb"))]
+        output_mneb: String,
+        /// The degree of the curves built from control polygons written by the `svg` command.
+        /// Other paths get the degree of their segments.
+        #[arg(short, long, default_value_t = 3)]
+        degree: usize,
+        /// The speed of SMIL animations, in frames per second.
+        #[arg(short, long, default_value_t = 60.0f32)]
+        framerate: f32,
+        /// Only import the shape of the paths, ignoring their animations.
+        #[arg(long)]
+        ignore_animations: bool,
+    },
+    /// Export every key frame as a row of CSV.
+    ExportCsv {
+        filename: String,
+        /// The name of the CSV file to output.
+        #[arg(default_value_t = String::from("out.csv"))]
+        output_csv: String,
+    },
+    /// Replace the key frames of an MNEB file with ones read from CSV.
+    ImportCsv {
+        filename: String,
+        /// The CSV file holding the key frames.
+        csv: String,
+        /// The name of the MNEB file to output.
+        #[arg(default_value_t = String::from("out.mneb"))]
+        output_mneb: String,
+    },
+    /// Check files for structural problems, exiting with an error if any are found.
+    Validate {
+        /// The file to check, or a pattern matching several files. Files ending in `.json` are
+        /// read as JSON from the `convert` command.
+        filename: String,
+        /// Print the results as JSON.
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Print a summary of what a file holds. Several files are summarized as a table.
+    Info {
+        /// The file to summarize, or a pattern matching several files.
+        filename: String,
+    },
+    /// Print a hex dump labeling the field each byte was read into, and list unread bytes.
+    Coverage {
+        filename: String,
+        /// Only list the bytes that were never read.
+        #[arg(short, long)]
+        gaps: bool,
+        /// Print the spans and gaps as JSON.
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Report how the unknown fields vary across many files.
+    Stats {
+        /// A pattern matching the files to compare.
+        filename: String,
+        /// How many of the most common values to list for each field.
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Print the statistics as JSON.
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Compare two files, exiting with an error if they differ.
+    Diff {
+        old: String,
+        new: String,
+        /// Print the changes as JSON.
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Copy some of the curves of a file into a new file.
+    Extract {
+        filename: String,
+        /// The name of a curve to copy. Can be given more than once.
+        #[arg(short, long = "name", required = true)]
+        names: Vec<String>,
+        /// The name of the MNEB file to output.
+        #[arg(short, long, default_value_t = String::from("out.mneb"))]
+        output: String,
+    },
+    /// Combine the curves of several files into one file.
+    Merge {
+        #[arg(required = true)]
+        filenames: Vec<String>,
+        /// The name of the MNEB file to output.
+        #[arg(short, long, default_value_t = String::from("out.mneb"))]
+        output: String,
+        /// What to do with curves that have the same name: error, rename, skip or replace.
+        #[arg(long, default_value = "error")]
+        on_collision: Collision,
+        /// Which frame count and looping to keep if the files disagree: strict, first or
+        /// longest.
+        #[arg(long, default_value = "strict")]
+        header: HeaderPolicy,
+    },
+    /// Write each curve of a file to a file of its own. Existing files are never overwritten.
+    Split {
+        filename: String,
+        #[arg(short, long, default_value_t = String::from("out"))]
+        /// The folder to output the files to.
+        output_folder_name: String,
+    },
+    /// Move, scale, rotate or mirror curves along with their key frames. Mirroring, scaling and
+    /// rotation happen first, around the origin, followed by translation.
+    Transform {
+        filename: String,
+        /// The name of the MNEB file to output.
+        #[arg(default_value_t = String::from("out.mneb"))]
+        output_mneb: String,
+        /// The name of a curve to transform. Can be given more than once. Defaults to every curve.
+        #[arg(short, long = "curve")]
+        curves: Vec<String>,
+        /// The offset to move by, as `x,y`.
+        #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+        translate: Option<(f32, f32)>,
+        /// The factor to scale by, as `s` or `x,y`.
+        #[arg(long, value_parser = parse_scale, allow_hyphen_values = true)]
+        scale: Option<(f32, f32)>,
+        /// The angle to rotate by, in degrees, turning the x axis towards the y axis.
+        #[arg(long, allow_hyphen_values = true)]
+        rotate: Option<f32>,
+        /// Negate x, mirroring left and right.
+        #[arg(long)]
+        mirror_x: bool,
+        /// Negate y, mirroring up and down.
+        #[arg(long)]
+        mirror_y: bool,
+        /// The point to mirror, scale and rotate around, as `x,y`.
+        #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+        origin: Option<(f32, f32)>,
+        /// The offset to move z by.
+        #[arg(long, allow_hyphen_values = true)]
+        translate_z: Option<f32>,
+        /// The factor to scale z by.
+        #[arg(long, allow_hyphen_values = true)]
+        scale_z: Option<f32>,
+    },
+    /// Change the timing of an animation. Trimming happens first, then scaling, then offsetting.
+    Retime {
+        filename: String,
+        /// The name of the MNEB file to output.
+        #[arg(default_value_t = String::from("out.mneb"))]
+        output_mneb: String,
+        /// How many times longer the animation should be.
+        #[arg(short, long, default_value_t = 1.0f32, conflicts_with = "frame_count")]
+        scale: f32,
+        /// The frame count to stretch the animation to, instead of giving a scale.
+        #[arg(long)]
+        frame_count: Option<u16>,
+        /// How many frames to delay the animation by.
+        #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+        offset: i32,
+        /// The first frame to keep, which becomes frame 0.
+        #[arg(long)]
+        start: Option<u16>,
+        /// The last frame to keep. Defaults to the frame count.
+        #[arg(long)]
+        end: Option<u16>,
+        /// What to do with key frames that land on the same frame: last, first or error.
+        #[arg(long, default_value = "last")]
+        on_collision: FrameCollision,
+    },
+    /// Remove key frames that interpolation already reproduces, and report how many were removed.
+    Simplify {
+        filename: String,
+        /// The name of the MNEB file to output.
+        #[arg(default_value_t = String::from("out.mneb"))]
+        output_mneb: String,
+        /// How far nodes may stray from their original positions, in pixels.
+        #[arg(short, long, default_value_t = 1.0f32)]
+        tolerance: f32,
+        /// Print the report as JSON.
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Write the posed position of every node on every frame as JSON or CSV.
+    Bake {
+        filename: String,
+        /// The name of the .json or .csv file to output.
+        #[arg(default_value_t = String::from("out.json"))]
+        output: String,
+        /// Make the JSON output pretty.
+        #[arg(short, long)]
+        pretty: bool,
+        /// Also write an MNEB file with a key frame on every frame for every animated node.
+        #[arg(long)]
+        dense: Option<String>,
+    },
+    /// Render frames to PNG files without opening a window.
+    Render {
+        filename: String,
+        /// The folder to output the frames to.
+        #[arg(short, long, default_value_t = String::from("frames"))]
+        output_folder_name: String,
+        /// Only render this frame.
+        #[arg(long, conflicts_with_all = ["start", "end"])]
+        frame: Option<u16>,
+        /// The first frame to render.
+        #[arg(short, long)]
+        start: Option<u16>,
+        /// The frame to stop rendering at. Defaults to the frame count.
+        #[arg(short, long)]
+        end: Option<u16>,
+        #[command(flatten)]
+        view: ViewArgs,
+    },
+    /// Export the animation as an animated GIF or PNG.
+    Clip {
+        filename: String,
+        /// The name of the file to output. The format is picked from the extension, which can
+        /// be `.gif`, `.png` or `.apng`.
+        #[arg(default_value_t = String::from("out.gif"))]
+        output: String,
+        /// The speed of the animation, in frames per second.
+        #[arg(short, long, default_value_t = 60.0f32)]
+        framerate: f32,
+        /// How many images per second to capture.
+        #[arg(long, default_value_t = 30.0f32)]
+        fps: f32,
+        #[command(flatten)]
+        view: ViewArgs,
+    },
+    /// Export the curves as an SVG, either at one frame or animated.
+    Svg {
+        filename: String,
+        /// The name of the SVG file to output.
+        #[arg(default_value_t = String::from("out.svg"))]
+        output: String,
+        /// The frame to draw the curves at.
+        #[arg(long, default_value_t = 0, conflicts_with = "animated")]
+        frame: u16,
+        /// Play the animation in the SVG, using SMIL.
+        #[arg(short, long)]
+        animated: bool,
+        /// The speed of the animation, in frames per second.
+        #[arg(short, long, default_value_t = 60.0f32)]
+        framerate: f32,
+    },
+    /// Export the curves and their animation as glTF.
+    Gltf {
+        filename: String,
+        /// The name of the file to output. The format is picked from the extension, which can
+        /// be `.gltf` or `.glb`.
+        #[arg(default_value_t = String::from("out.gltf"))]
+        output: String,
+        /// The speed of the animation, in frames per second.
+        #[arg(short, long, default_value_t = 60.0f32)]
+        framerate: f32,
+        /// How many glTF units one MNEB unit spans.
+        #[arg(short, long, default_value_t = 0.01f32)]
+        scale: f32,
+    },
+}
+
+/// How to frame and draw curves when rendering without a window.
+#[derive(clap::Args, Debug)]
+struct ViewArgs {
+    #[arg(long, default_value_t = 640)]
+    width: u32,
+    #[arg(long, default_value_t = 480)]
+    height: u32,
+    /// The point to center the view on, as `x,y`. Defaults to the middle of the curves.
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+    center: Option<(f32, f32)>,
+    /// The zoom level, as shown in the viewer. Defaults to fitting the curves in view.
+    #[arg(short, long)]
+    zoom: Option<f32>,
+    /// The thickness of curves, in pixels.
+    #[arg(short, long, default_value_t = 2.0f32)]
+    thickness: f32,
+    /// Draw the control polygon over the curves.
+    #[arg(short, long)]
+    control_polygon: bool,
+}
+
+impl ViewArgs {
+    /// Builds the options for rendering `frames` of `mneb_file`. Whatever part of the camera
+    /// wasn't given is picked by fitting the curves in view.
+    fn options(&self, mneb_file: &mneb::MNEBFile, frames: &[f32]) -> RenderOptions {
+        let fit = Camera::fit(mneb_file, frames, self.width, self.height);
+        RenderOptions {
+            width: self.width,
+            height: self.height,
+            camera: Camera {
+                center: self.center.unwrap_or(fit.center),
+                zoom: self.zoom.unwrap_or(fit.zoom),
+            },
+            thickness: self.thickness,
+            control_polygon: self.control_polygon,
+        }
+    }
+}
+
+/// The result of validating one file.
+#[derive(serde::Serialize)]
+struct ValidationReport {
+    file: String,
+    /// Why the file couldn't be parsed, if it couldn't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    issues: Vec<lint::Issue>,
+    /// Issues that don't count as problems, such as names that fill their field.
+    warnings: Vec<lint::Issue>,
 }
 
 #[derive(Parser, Debug)]
@@ -38,10 +379,21 @@ fn main() -> Result<()> {
     let args = Args::parse();
 
     match &args.usage {
+        #[cfg(feature = "viewer")]
         Usage::Animate {
             framerate,
             filename,
+            control_polygon,
+            in_frame,
+            out_frame,
         } => {
+            if let (Some(in_frame), Some(out_frame)) = (in_frame, out_frame) {
+                anyhow::ensure!(
+                    in_frame < out_frame,
+                    "The in frame must come before the out frame."
+                );
+            }
+
             if filename.contains('*') && filename.contains(".mneb")
             // to be entirely sure we're only rendering mneb files
             {
@@ -69,7 +421,13 @@ fn main() -> Result<()> {
                 if !mneb_files.is_empty() {
                     // sort alphabetically before playing
                     mneb_files.sort_by(|a, b| a.0.cmp(&b.0));
-                    animate::animate_files(mneb_files, *framerate);
+                    animate::animate_files(
+                        mneb_files,
+                        *framerate,
+                        *control_polygon,
+                        *in_frame,
+                        *out_frame,
+                    );
                 } else {
                     println!("No valid MNEB files found matching pattern: {}", filename);
                 }
@@ -78,9 +436,21 @@ fn main() -> Result<()> {
                 let file = fs::read(filename)?;
                 let mneb_file = mneb::MNEBFile::from_bytes(&file)?;
                 if mneb_file.has_curves() {
-                    let filename =
-                        format!("{}", Path::new(filename).file_name().unwrap().display());
-                    animate::animate_file(mneb_file, *framerate, filename);
+                    let filename = format!(
+                        "{}",
+                        std::path::Path::new(filename)
+                            .file_name()
+                            .unwrap()
+                            .display()
+                    );
+                    animate::animate_file(
+                        mneb_file,
+                        *framerate,
+                        filename,
+                        *control_polygon,
+                        *in_frame,
+                        *out_frame,
+                    );
                 } else {
                     // nothing to do
                     println!("File does not have curves to render.");
@@ -137,7 +507,960 @@ fn main() -> Result<()> {
                 fs::write(output_json, json)?;
             }
         }
+
+        Usage::Import {
+            filename,
+            output_mneb,
+            output_folder_name,
+        } => {
+            if filename.contains('*') && filename.contains(".json") {
+                let mut mneb_files = Vec::new();
+                for entry in glob(filename).expect("Failed to read glob pattern.") {
+                    match entry {
+                        Ok(path) => match fs::read_to_string(&path) {
+                            Ok(json) => match serde_json::from_str::<mneb::MNEBFile>(&json) {
+                                Ok(mneb_file) => {
+                                    // "file.mneb.json" goes back to "file.mneb"
+                                    let name = format!("{}", path.file_stem().unwrap().display());
+
+                                    mneb_files.push((name, mneb_file));
+                                }
+
+                                Err(e) => {
+                                    println!("Skipping {}: {}", path.display(), e);
+                                }
+                            },
+
+                            Err(e) => {
+                                println!("Skipping {}: {}", path.display(), e);
+                            }
+                        },
+
+                        Err(e) => {
+                            println!("Error matching glob pattern: {:?}", e);
+                        }
+                    }
+                }
+
+                for (name, mneb_file) in mneb_files {
+                    if !fs::exists(output_folder_name)? {
+                        fs::create_dir(output_folder_name)?;
+                    }
+                    write_mneb(&mneb_file, format!("{}/{}", output_folder_name, name))?;
+                }
+            } else {
+                let json = fs::read_to_string(filename)?;
+                let mneb_file: mneb::MNEBFile = serde_json::from_str(&json)?;
+                write_mneb(&mneb_file, output_mneb)?;
+            }
+        }
+
+        Usage::ImportSvg {
+            filename,
+            output_mneb,
+            degree,
+            framerate,
+            ignore_animations,
+        } => {
+            anyhow::ensure!(*framerate > 0.0, "The framerate must be positive.");
+
+            let text = fs::read_to_string(filename)?;
+            let options = svg::SvgImportOptions {
+                degree: *degree,
+                framerate: *framerate,
+                animations: !ignore_animations,
+            };
+            let import = svg::from_svg(&text, &options)?;
+            for id in &import.skipped {
+                eprintln!("Skipping path {}, which has fewer than 2 points.", id);
+            }
+
+            let mneb_file = import.mneb_file;
+            anyhow::ensure!(mneb_file.has_curves(), "File does not have any paths.");
+
+            write_mneb(&mneb_file, output_mneb)?;
+        }
+
+        Usage::ExportCsv {
+            filename,
+            output_csv,
+        } => {
+            let mneb_file = read_mneb(filename, "export")?;
+
+            tracks::write_csv(&mneb_file, fs::File::create(output_csv)?)?;
+        }
+
+        Usage::ImportCsv {
+            filename,
+            csv,
+            output_mneb,
+        } => {
+            let mut mneb_file = read_mneb(filename, "import")?;
+
+            tracks::read_csv(&mut mneb_file, fs::File::open(csv)?)?;
+
+            write_mneb(&mneb_file, output_mneb)?;
+        }
+
+        Usage::Validate { filename, json } => {
+            let paths: Vec<std::path::PathBuf> = if filename.contains('*') {
+                glob(filename)
+                    .expect("Failed to read glob pattern.")
+                    .filter_map(|entry| entry.ok())
+                    .collect()
+            } else {
+                vec![filename.into()]
+            };
+            anyhow::ensure!(!paths.is_empty(), "No files match {}.", filename);
+
+            let mut reports = Vec::new();
+            for path in paths {
+                let mut report = ValidationReport {
+                    file: format!("{}", path.display()),
+                    error: None,
+                    issues: Vec::new(),
+                    warnings: Vec::new(),
+                };
+
+                match read_mneb_or_json(&path) {
+                    Ok(mneb_file) => {
+                        (report.warnings, report.issues) = lint::validate(&mneb_file)
+                            .into_iter()
+                            .partition(|issue| issue.severity() == lint::Severity::Warning);
+                    }
+                    Err(e) => report.error = Some(e.to_string()),
+                }
+
+                reports.push(report);
+            }
+
+            let failed = reports
+                .iter()
+                .filter(|report| report.error.is_some() || !report.issues.is_empty())
+                .count();
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&reports)?);
+            } else {
+                for report in &reports {
+                    if let Some(error) = &report.error {
+                        println!("{}: {}", report.file, error);
+                    }
+
+                    for issue in &report.issues {
+                        println!("{}: {}", report.file, issue);
+                    }
+
+                    for warning in &report.warnings {
+                        println!("{}: warning: {}", report.file, warning);
+                    }
+                }
+
+                println!("{} of {} files have problems.", failed, reports.len());
+            }
+
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+
+        Usage::Info { filename } => {
+            if filename.contains('*') {
+                let mut rows = vec![
+                    [
+                        "File",
+                        "Endian",
+                        "Lock",
+                        "Frames",
+                        "Looped",
+                        "Unk 8",
+                        "Curves",
+                        "Points",
+                        "Key sets",
+                        "Demo sets",
+                    ]
+                    .map(String::from),
+                ];
+
+                for entry in glob(filename).expect("Failed to read glob pattern.") {
+                    match entry {
+                        Ok(path) => match fs::read(&path)
+                            .map_err(mneb::MnebError::from)
+                            .and_then(|bytes| mneb::MNEBFile::from_bytes(&bytes))
+                        {
+                            Ok(mneb_file) => rows.push(info_row(&path, &mneb_file)),
+                            Err(e) => println!("Skipping {}: {}", path.display(), e),
+                        },
+
+                        Err(e) => {
+                            println!("Error matching glob pattern: {:?}", e);
+                        }
+                    }
+                }
+
+                print_table(&rows);
+            } else {
+                let file = fs::read(filename)?;
+                let mneb_file = mneb::MNEBFile::from_bytes(&file)?;
+                print_info(filename, &mneb_file);
+            }
+        }
+
+        Usage::Coverage {
+            filename,
+            gaps,
+            json,
+        } => {
+            let raw = fs::read(filename)?;
+            let (result, spans) = mneb::MNEBFile::from_bytes_traced(&raw);
+            let unread = coverage::gaps(&raw, &spans);
+
+            if *json {
+                let report = if *gaps {
+                    serde_json::json!({ "gaps": unread })
+                } else {
+                    serde_json::json!({ "spans": spans, "gaps": unread })
+                };
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                if !*gaps {
+                    print!("{}", coverage::hex_dump(&raw, &spans));
+                    println!();
+                }
+
+                println!("Unread bytes:");
+                for gap in &unread {
+                    println!(
+                        "  {:#010X}..{:#010X} ({:#X} bytes, {:?})",
+                        gap.start,
+                        gap.start + gap.len,
+                        gap.len,
+                        gap.kind
+                    );
+                }
+
+                let num_unread: usize = unread.iter().map(|gap| gap.len).sum();
+                println!(
+                    "{:#X} of {:#X} bytes read.",
+                    raw.len() - num_unread,
+                    raw.len()
+                );
+            }
+
+            // the spans are still useful when parsing fails, but the error is reported too
+            result?;
+        }
+
+        Usage::Stats {
+            filename,
+            top,
+            json,
+        } => {
+            let mut corpus = stats::Corpus::new();
+            for entry in glob(filename).expect("Failed to read glob pattern.") {
+                match entry {
+                    Ok(path) => match fs::read(&path)
+                        .map_err(mneb::MnebError::from)
+                        .and_then(|bytes| mneb::MNEBFile::from_bytes(&bytes))
+                    {
+                        Ok(mneb_file) => corpus.add(&mneb_file),
+                        Err(e) => eprintln!("Skipping {}: {}", path.display(), e),
+                    },
+
+                    Err(e) => {
+                        eprintln!("Error matching glob pattern: {:?}", e);
+                    }
+                }
+            }
+
+            anyhow::ensure!(corpus.num_files() > 0, "No files match {}.", filename);
+            let fields = corpus.stats(*top);
+
+            if *json {
+                let report = serde_json::json!({
+                    "files": corpus.num_files(),
+                    "fields": fields,
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                let mut rows = vec![
+                    ["Field", "Count", "Min", "Max", "Distinct", "Most common"]
+                        .into_iter()
+                        .map(String::from)
+                        .chain(
+                            stats::KNOWN_FIELDS
+                                .iter()
+                                .map(|field| format!("r({})", field)),
+                        )
+                        .collect::<Vec<String>>(),
+                ];
+
+                for field in &fields {
+                    let common: Vec<String> = field
+                        .histogram
+                        .iter()
+                        .take(3)
+                        .map(|value| format!("{} x{}", value.value, value.count))
+                        .collect();
+
+                    let mut row = vec![
+                        field.name.clone(),
+                        field.count.to_string(),
+                        field.min.to_string(),
+                        field.max.to_string(),
+                        field.distinct.to_string(),
+                        common.join(", "),
+                    ];
+                    row.extend(field.correlations.iter().map(|correlation| {
+                        correlation
+                            .r
+                            .map_or("-".to_string(), |r| format!("{:.2}", r))
+                    }));
+                    rows.push(row);
+                }
+
+                println!("{} files", corpus.num_files());
+                print_table(&rows);
+            }
+        }
+
+        Usage::Diff { old, new, json } => {
+            let old_file = mneb::MNEBFile::from_bytes(&fs::read(old)?)?;
+            let new_file = mneb::MNEBFile::from_bytes(&fs::read(new)?)?;
+            let changes = diff::diff(&old_file, &new_file);
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&changes)?);
+            } else {
+                println!("--- {}", old);
+                println!("+++ {}", new);
+                for change in &changes {
+                    println!("{}", change);
+                }
+                println!("{} changes.", changes.len());
+            }
+
+            if !changes.is_empty() {
+                std::process::exit(1);
+            }
+        }
+
+        Usage::Extract {
+            filename,
+            names,
+            output,
+        } => {
+            let file = fs::read(filename)?;
+            let mneb_file = mneb::MNEBFile::from_bytes(&file)?;
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+            let extracted = merge::extract(&mneb_file, &names)?;
+            write_mneb(&extracted, output)?;
+        }
+
+        Usage::Merge {
+            filenames,
+            output,
+            on_collision,
+            header,
+        } => {
+            let mut mneb_files = Vec::new();
+            for filename in filenames {
+                let file = fs::read(filename)?;
+                mneb_files.push(mneb::MNEBFile::from_bytes(&file)?);
+            }
+
+            let merged = merge::merge(&mneb_files, *on_collision, *header)?;
+            write_mneb(&merged, output)?;
+        }
+
+        Usage::Split {
+            filename,
+            output_folder_name,
+        } => {
+            let mneb_file = read_mneb(filename, "split")?;
+
+            let stem = format!(
+                "{}",
+                std::path::Path::new(filename)
+                    .file_stem()
+                    .unwrap()
+                    .display()
+            );
+
+            let names: Vec<&str> = mneb_file.curves.iter().map(|c| c.name.as_str()).collect();
+            let paths: Vec<std::path::PathBuf> = split_file_names(&stem, &names)
+                .into_iter()
+                .map(|name| std::path::Path::new(output_folder_name).join(name))
+                .collect();
+            for path in &paths {
+                anyhow::ensure!(!fs::exists(path)?, "{} already exists.", path.display());
+            }
+
+            if !fs::exists(output_folder_name)? {
+                fs::create_dir(output_folder_name)?;
+            }
+            for (path, part) in paths.iter().zip(merge::split(&mneb_file)) {
+                let mut file = fs::File::create_new(path)?;
+                part.write_to(&mut file)?;
+            }
+        }
+
+        Usage::Transform {
+            filename,
+            output_mneb,
+            curves,
+            translate,
+            scale,
+            rotate,
+            mirror_x,
+            mirror_y,
+            origin,
+            translate_z,
+            scale_z,
+        } => {
+            let mut linear = Transform::IDENTITY;
+            if *mirror_x {
+                linear = linear.then(&Transform::mirror_x());
+            }
+            if *mirror_y {
+                linear = linear.then(&Transform::mirror_y());
+            }
+            if let Some((x, y)) = scale {
+                linear = linear.then(&Transform::scale(*x, *y));
+            }
+            if let Some(degrees) = rotate {
+                linear = linear.then(&Transform::rotate(*degrees));
+            }
+
+            let mut combined = linear.around(origin.unwrap_or((0.0, 0.0)));
+            if let Some((x, y)) = translate {
+                combined = combined.then(&Transform::translate(*x, *y));
+            }
+            if let Some(z) = scale_z {
+                combined = combined.then(&Transform::scale_z(*z));
+            }
+            if let Some(z) = translate_z {
+                combined = combined.then(&Transform::translate_z(*z));
+            }
+
+            let mut mneb_file = read_mneb(filename, "transform")?;
+
+            let names: Vec<&str> = curves.iter().map(String::as_str).collect();
+            transform::transform_curves(&mut mneb_file, &names, &combined)?;
+
+            write_mneb(&mneb_file, output_mneb)?;
+        }
+
+        Usage::Retime {
+            filename,
+            output_mneb,
+            scale,
+            frame_count,
+            offset,
+            start,
+            end,
+            on_collision,
+        } => {
+            let mut mneb_file = read_mneb(filename, "retime")?;
+
+            let range = (start.is_some() || end.is_some())
+                .then(|| (start.unwrap_or(0), end.unwrap_or(mneb_file.frame_count)));
+            let options = RetimeOptions {
+                range,
+                scale: *scale,
+                frame_count: *frame_count,
+                offset: *offset,
+                collision: *on_collision,
+            };
+            retime::retime(&mut mneb_file, &options)?;
+
+            write_mneb(&mneb_file, output_mneb)?;
+        }
+
+        Usage::Simplify {
+            filename,
+            output_mneb,
+            tolerance,
+            json,
+        } => {
+            anyhow::ensure!(*tolerance >= 0.0, "The tolerance can't be negative.");
+
+            let mut mneb_file = read_mneb(filename, "simplify")?;
+
+            let reductions = simplify::simplify(&mut mneb_file, *tolerance);
+            write_mneb(&mneb_file, output_mneb)?;
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&reductions)?);
+            } else {
+                let mut rows =
+                    vec![["Curve", "Node", "Before", "After", "Max error"].map(String::from)];
+                for reduction in &reductions {
+                    rows.push([
+                        reduction.curve.clone(),
+                        reduction.node_index.to_string(),
+                        reduction.before.to_string(),
+                        reduction.after.to_string(),
+                        format!("{:.2}", reduction.max_error),
+                    ]);
+                }
+                print_table(&rows);
+
+                let before: usize = reductions.iter().map(|r| r.before).sum();
+                let after: usize = reductions.iter().map(|r| r.after).sum();
+                println!("\n{} key frames reduced to {}.", before, after);
+            }
+        }
+
+        Usage::Bake {
+            filename,
+            output,
+            pretty,
+            dense,
+        } => {
+            let is_csv = output_format(output, &[("json", false), ("csv", true)])?;
+
+            let mut mneb_file = read_mneb(filename, "bake")?;
+            let baked = bake::bake(&mneb_file)?;
+            if is_csv {
+                baked.write_csv(std::io::BufWriter::new(fs::File::create(output)?))?;
+            } else {
+                let json = if *pretty {
+                    serde_json::to_string_pretty(&baked)?
+                } else {
+                    serde_json::to_string(&baked)?
+                };
+                fs::write(output, json)?;
+            }
+
+            if let Some(dense) = dense {
+                bake::bake_key_frames(&mut mneb_file)?;
+                write_mneb(&mneb_file, dense)?;
+            }
+        }
+
+        Usage::Render {
+            filename,
+            output_folder_name,
+            frame,
+            start,
+            end,
+            view,
+        } => {
+            let mneb_file = read_mneb(filename, "render")?;
+
+            let frames = frame_range(&mneb_file, *frame, *start, *end);
+            anyhow::ensure!(!frames.is_empty(), "No frames to render.");
+
+            let frame_times: Vec<f32> = frames.iter().map(|&f| f as f32).collect();
+            let options = view.options(&mneb_file, &frame_times);
+
+            if !fs::exists(output_folder_name)? {
+                fs::create_dir(output_folder_name)?;
+            }
+
+            let name = format!(
+                "{}",
+                std::path::Path::new(filename)
+                    .file_stem()
+                    .unwrap()
+                    .display()
+            );
+            for frame in frames {
+                let canvas = render::render_frame(&mneb_file, frame as f32, &options);
+                let out =
+                    fs::File::create(format!("{}/{}_{:04}.png", output_folder_name, name, frame))?;
+                canvas.write_png(std::io::BufWriter::new(out))?;
+            }
+        }
+
+        Usage::Clip {
+            filename,
+            output,
+            framerate,
+            fps,
+            view,
+        } => {
+            anyhow::ensure!(
+                *framerate > 0.0 && *fps > 0.0,
+                "The framerate and FPS must be positive."
+            );
+
+            let is_gif = output_format(output, &[("gif", true), ("png", false), ("apng", false)])?;
+
+            let mneb_file = read_mneb(filename, "render")?;
+
+            let frame_times = render::clip_frames(&mneb_file, *framerate, *fps);
+            anyhow::ensure!(!frame_times.is_empty(), "No frames to render.");
+
+            let options = view.options(&mneb_file, &frame_times);
+            let frames = frame_times
+                .iter()
+                .map(|&frame| render::render_frame(&mneb_file, frame, &options));
+
+            let out = std::io::BufWriter::new(fs::File::create(output)?);
+            if is_gif {
+                render::write_gif(frames, *fps, mneb_file.is_looped, out)?;
+            } else {
+                render::write_apng(frames, *fps, mneb_file.is_looped, out)?;
+            }
+        }
+
+        Usage::Svg {
+            filename,
+            output,
+            frame,
+            animated,
+            framerate,
+        } => {
+            anyhow::ensure!(*framerate > 0.0, "The framerate must be positive.");
+
+            let mneb_file = read_mneb(filename, "render")?;
+
+            let svg = if *animated {
+                svg::to_animated_svg(&mneb_file, *framerate)
+            } else {
+                svg::to_svg(&mneb_file, *frame as f32)
+            };
+            fs::write(output, svg)?;
+        }
+
+        Usage::Gltf {
+            filename,
+            output,
+            framerate,
+            scale,
+        } => {
+            anyhow::ensure!(*framerate > 0.0, "The framerate must be positive.");
+
+            let is_binary = output_format(output, &[("gltf", false), ("glb", true)])?;
+
+            let mneb_file = read_mneb(filename, "export")?;
+
+            let name = format!(
+                "{}",
+                std::path::Path::new(filename)
+                    .file_stem()
+                    .unwrap()
+                    .display()
+            );
+            let options = gltf::GltfOptions {
+                framerate: *framerate,
+                scale: *scale,
+            };
+
+            let out = std::io::BufWriter::new(fs::File::create(output)?);
+            if is_binary {
+                gltf::write_glb(&mneb_file, &name, &options, out)?;
+            } else {
+                gltf::write_gltf(&mneb_file, &name, &options, out)?;
+            }
+        }
     }
 
     Ok(())
 }
+
+/// The smallest box holding every control point of `curve` and every position its key frames
+/// move to, as the minimum and maximum corners.
+fn curve_bounds(curve: &mneb::Curve) -> Option<((i16, i16), (i16, i16))> {
+    let points = curve
+        .control_points
+        .iter()
+        .map(|point| (point.x, point.y))
+        .chain(
+            curve
+                .key_frame_sets
+                .iter()
+                .flat_map(|set| &set.key_frames)
+                .filter(|key_frame| key_frame.is_active)
+                .map(|key_frame| (key_frame.x, key_frame.y)),
+        );
+
+    points.fold(None, |bounds, (x, y)| match bounds {
+        None => Some(((x, y), (x, y))),
+        Some((min, max)) => Some(((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))),
+    })
+}
+
+fn print_info(filename: &str, mneb_file: &mneb::MNEBFile) {
+    println!("{}", filename);
+    println!("  Endian: {:?}", mneb_file.endian);
+    println!("  Lock: {:#X}", mneb_file.lock);
+    println!("  Frame count: {}", mneb_file.frame_count);
+    println!("  Looped: {}", mneb_file.is_looped);
+    println!("  Unknown 0x8: {:#010X}", mneb_file._unk_8);
+
+    if mneb_file.has_curves() {
+        println!("  Curves: {}", mneb_file.curves.len());
+        for curve in &mneb_file.curves {
+            let degree = curve
+                .degree()
+                .map_or("unknown".to_string(), |degree| degree.to_string());
+            let bounds = curve_bounds(curve).map_or("none".to_string(), |(min, max)| {
+                format!("({}, {}) to ({}, {})", min.0, min.1, max.0, max.1)
+            });
+
+            println!(
+                "    {}: {} control points, {} knots, {} key frame sets, degree {}, bounds {}",
+                curve.name,
+                curve.control_points.len(),
+                curve.knots.len(),
+                curve.key_frame_sets.len(),
+                degree,
+                bounds
+            );
+        }
+    } else {
+        println!("  Demo option sets: {}", mneb_file.demo_option_sets.len());
+        for set in &mneb_file.demo_option_sets {
+            println!("    {}: {} options", set.name, set.demo_options.len());
+            for option in &set.demo_options {
+                println!("      {} = {}", option.name, option.value);
+            }
+        }
+    }
+}
+
+/// A row of the table printed by `info` for several files.
+fn info_row(path: &std::path::Path, mneb_file: &mneb::MNEBFile) -> [String; 10] {
+    [
+        format!("{}", path.display()),
+        format!("{:?}", mneb_file.endian),
+        format!("{:#X}", mneb_file.lock),
+        mneb_file.frame_count.to_string(),
+        mneb_file.is_looped.to_string(),
+        format!("{:#X}", mneb_file._unk_8),
+        mneb_file.curves.len().to_string(),
+        mneb_file
+            .curves
+            .iter()
+            .map(|curve| curve.control_points.len())
+            .sum::<usize>()
+            .to_string(),
+        mneb_file
+            .curves
+            .iter()
+            .map(|curve| curve.key_frame_sets.len())
+            .sum::<usize>()
+            .to_string(),
+        mneb_file.demo_option_sets.len().to_string(),
+    ]
+}
+
+/// Prints rows with their columns lined up. The first row is the header.
+fn print_table<R: AsRef<[String]>>(rows: &[R]) {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in rows {
+        for (i, cell) in row.as_ref().iter().enumerate() {
+            if i == widths.len() {
+                widths.push(0);
+            }
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = row
+            .as_ref()
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+
+        if i == 0 {
+            let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+            println!("{}", rule.join("  "));
+        }
+    }
+}
+
+/// Replaces anything in `name` that isn't a letter, a digit, `-` or `_` with `_`, so it can be
+/// used as part of a file name without leaving its folder.
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Names the files `split` writes for curves called `names`, as `{stem}_{name}.mneb`. Curve
+/// names come from the file, so they can't be trusted as paths, and several may end up the same;
+/// those get the curve's index instead, or the first free number after it.
+fn split_file_names(stem: &str, names: &[&str]) -> Vec<String> {
+    let mut file_names: Vec<String> = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let name = safe_file_name(name);
+        let mut file_name = format!("{}_{}.mneb", stem, name);
+        if name.is_empty() || file_names.contains(&file_name) {
+            file_name = (i..)
+                .map(|n| format!("{}_{}.mneb", stem, n))
+                .find(|file_name| !file_names.contains(file_name))
+                .unwrap_or_default();
+        }
+
+        file_names.push(file_name);
+    }
+
+    file_names
+}
+
+/// Reads the MNEB file at `path`, failing if it doesn't have curves to `action`.
+fn read_mneb(path: impl AsRef<std::path::Path>, action: &str) -> Result<mneb::MNEBFile> {
+    let mneb_file = mneb::MNEBFile::from_bytes(&fs::read(path)?)?;
+    anyhow::ensure!(
+        mneb_file.has_curves(),
+        "File does not have curves to {}.",
+        action
+    );
+    Ok(mneb_file)
+}
+
+/// Writes `mneb_file` to `path`.
+fn write_mneb(mneb_file: &mneb::MNEBFile, path: impl AsRef<std::path::Path>) -> Result<()> {
+    fs::write(path, mneb_file.to_bytes()?)?;
+    Ok(())
+}
+
+/// Picks the format of the output file at `path` from its extension, given as pairs of
+/// lowercase extensions and the formats they stand for.
+fn output_format<T: Copy>(path: &str, formats: &[(&str, T)]) -> Result<T> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+
+    if let Some(&(_, format)) = formats
+        .iter()
+        .find(|(name, _)| extension.as_deref() == Some(*name))
+    {
+        return Ok(format);
+    }
+
+    let names: Vec<String> = formats
+        .iter()
+        .map(|(name, _)| format!(".{}", name))
+        .collect();
+    let (last, rest) = names.split_last().expect("no output formats");
+    anyhow::bail!("The output must be a {} or {} file.", rest.join(", "), last)
+}
+
+/// Reads an MNEB file, or JSON written by the `convert` command if the extension is `.json`.
+fn read_mneb_or_json(path: &std::path::Path) -> Result<mneb::MNEBFile> {
+    let is_json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    } else {
+        Ok(mneb::MNEBFile::from_bytes(&fs::read(path)?)?)
+    }
+}
+
+/// Parses a point given as `x,y`.
+fn parse_point(s: &str) -> Result<(f32, f32), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `x,y`, found `{}`", s))?;
+    let x = x.trim().parse().map_err(|e| format!("invalid x: {}", e))?;
+    let y = y.trim().parse().map_err(|e| format!("invalid y: {}", e))?;
+    Ok((x, y))
+}
+
+/// Parses a scale factor given as `s`, for both axes, or as `x,y`.
+fn parse_scale(s: &str) -> Result<(f32, f32), String> {
+    if s.contains(',') {
+        parse_point(s)
+    } else {
+        let scale = s
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid scale: {}", e))?;
+        Ok((scale, scale))
+    }
+}
+
+/// The frames to render from `mneb_file`, given the `--frame`, `--start` and `--end` options.
+fn frame_range(
+    mneb_file: &mneb::MNEBFile,
+    frame: Option<u16>,
+    start: Option<u16>,
+    end: Option<u16>,
+) -> Vec<u16> {
+    match frame {
+        Some(frame) => vec![frame],
+        None => (start.unwrap_or(0)..end.unwrap_or(mneb_file.frame_count)).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mneb::{ControlPoint, Curve, KeyFrame, KeyFrameSet};
+
+    #[test]
+    fn split_file_names_are_unique() {
+        assert_eq!(
+            split_file_names("x", &["a", "a", "../1", "", "x_1"]),
+            [
+                "x_a.mneb",
+                "x_1.mneb",
+                "x____1.mneb",
+                "x_3.mneb",
+                "x_x_1.mneb"
+            ]
+        );
+        // a later curve called `1` collides with the first fallback name
+        assert_eq!(
+            split_file_names("x", &["a", "a", "1"]),
+            ["x_a.mneb", "x_1.mneb", "x_2.mneb"]
+        );
+    }
+
+    #[test]
+    fn bounds_include_active_key_frames() {
+        let mut curve = Curve::new(
+            "a",
+            vec![
+                ControlPoint::new(0, 0, 0, 0),
+                ControlPoint::new(10, -5, 0, 0),
+            ],
+            Vec::new(),
+        );
+        curve.key_frame_sets = vec![KeyFrameSet::new(
+            1,
+            vec![
+                KeyFrame::new(0, true, 20, 30),
+                // inactive key frames sit at rest, so their positions don't count
+                KeyFrame::new(10, false, -100, -100),
+            ],
+        )];
+
+        assert_eq!(curve_bounds(&curve), Some(((0, -5), (20, 30))));
+        assert_eq!(curve_bounds(&Curve::default()), None);
+    }
+
+    #[test]
+    fn info_rows_count_every_curve() {
+        let mut first = Curve::new("a", vec![ControlPoint::default(); 2], Vec::new());
+        first.key_frame_sets = vec![KeyFrameSet::new(0, Vec::new())];
+        let second = Curve::new("b", vec![ControlPoint::default(); 3], Vec::new());
+        let mut mneb_file = mneb::MNEBFile::new(vec![first, second], 60, true);
+        mneb_file.lock = 0x10;
+
+        assert_eq!(
+            info_row(std::path::Path::new("a.mneb"), &mneb_file),
+            [
+                "a.mneb", "Big", "0x10", "60", "true", "0x0", "2", "5", "1", "0"
+            ]
+            .map(String::from)
+        );
+    }
+}
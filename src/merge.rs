@@ -0,0 +1,364 @@
+//! Moving curves between files: extracting some of them, merging several files into one, and
+//! splitting a file into one file per curve.
+
+use crate::error::MergeError;
+use crate::mneb::{CURVE_NAME_SIZE, Curve, MNEBFile};
+use std::str::FromStr;
+
+/// What to do when two merged files have curves with the same name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Collision {
+    /// Fail with [`MergeError::NameCollision`].
+    #[default]
+    Error,
+    /// Keep both, adding a number to the name of the later one. The end of the name is cut off
+    /// if the number wouldn't fit otherwise.
+    Rename,
+    /// Keep the earlier curve.
+    Skip,
+    /// Keep the later curve, in the place of the earlier one.
+    Replace,
+}
+
+/// How to pick `frame_count` and `is_looped` for merged files, which may disagree.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderPolicy {
+    /// Fail with [`MergeError::HeaderMismatch`] unless every file agrees.
+    #[default]
+    Strict,
+    /// Use the header of the first file.
+    First,
+    /// Use the header of the file with the most frames, so no animation is cut short.
+    Longest,
+}
+
+impl FromStr for Collision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "rename" => Ok(Self::Rename),
+            "skip" => Ok(Self::Skip),
+            "replace" => Ok(Self::Replace),
+            _ => Err(format!(
+                "expected `error`, `rename`, `skip` or `replace`, found `{}`",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for HeaderPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "first" => Ok(Self::First),
+            "longest" => Ok(Self::Longest),
+            _ => Err(format!(
+                "expected `strict`, `first` or `longest`, found `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// A copy of `mneb_file` without its curves or demo option sets.
+fn header(mneb_file: &MNEBFile) -> MNEBFile {
+    MNEBFile {
+        curves: Vec::new(),
+        demo_option_sets: Vec::new(),
+        endian: mneb_file.endian,
+        lock: mneb_file.lock,
+        frame_count: mneb_file.frame_count,
+        is_looped: mneb_file.is_looped,
+        _unk_8: mneb_file._unk_8,
+        source: None,
+    }
+}
+
+/// A copy of `mneb_file`'s header, holding `curves`.
+fn with_curves(mneb_file: &MNEBFile, curves: Vec<Curve>) -> MNEBFile {
+    MNEBFile {
+        curves,
+        ..header(mneb_file)
+    }
+}
+
+/// Builds a file holding the curves called `names`, in that order, with the same header as
+/// `mneb_file`.
+pub fn extract(mneb_file: &MNEBFile, names: &[&str]) -> Result<MNEBFile, MergeError> {
+    let curves = names
+        .iter()
+        .map(|&name| {
+            mneb_file
+                .curve(name)
+                .cloned()
+                .ok_or_else(|| MergeError::CurveNotFound {
+                    name: name.to_string(),
+                })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(with_curves(mneb_file, curves))
+}
+
+/// Builds one file per curve of `mneb_file`, each with the same header.
+pub fn split(mneb_file: &MNEBFile) -> Vec<MNEBFile> {
+    mneb_file
+        .curves
+        .iter()
+        .map(|curve| with_curves(mneb_file, vec![curve.clone()]))
+        .collect()
+}
+
+/// Picks the header for the merge of `files`.
+fn merged_header(files: &[&MNEBFile], policy: HeaderPolicy) -> Result<MNEBFile, MergeError> {
+    let first = files[0];
+
+    match policy {
+        HeaderPolicy::First => Ok(header(first)),
+        HeaderPolicy::Longest => {
+            // the first of the longest files, if there's a tie
+            let longest = files
+                .iter()
+                .copied()
+                .reduce(|a, b| if b.frame_count > a.frame_count { b } else { a })
+                .unwrap_or(first);
+
+            Ok(MNEBFile {
+                frame_count: longest.frame_count,
+                is_looped: longest.is_looped,
+                ..header(first)
+            })
+        }
+        HeaderPolicy::Strict => {
+            for other in &files[1..] {
+                if other.frame_count != first.frame_count {
+                    return Err(MergeError::HeaderMismatch {
+                        field: "frame_count",
+                        first: first.frame_count.to_string(),
+                        other: other.frame_count.to_string(),
+                    });
+                }
+
+                if other.is_looped != first.is_looped {
+                    return Err(MergeError::HeaderMismatch {
+                        field: "is_looped",
+                        first: first.is_looped.to_string(),
+                        other: other.is_looped.to_string(),
+                    });
+                }
+            }
+
+            Ok(header(first))
+        }
+    }
+}
+
+/// `name` with `_{i}` added, cutting the end off `name` if needed to fit in a curve name.
+fn numbered(name: &str, i: usize) -> String {
+    let suffix = format!("_{}", i);
+    let mut end = name.len().min(CURVE_NAME_SIZE.saturating_sub(suffix.len()));
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}{}", &name[..end], suffix)
+}
+
+/// Merges the curves of `files` into one file, in order. Files without curves are ignored.
+/// Other header fields, such as the byte order, come from the first file with curves.
+pub fn merge(
+    files: &[MNEBFile],
+    collision: Collision,
+    header: HeaderPolicy,
+) -> Result<MNEBFile, MergeError> {
+    let files: Vec<&MNEBFile> = files.iter().filter(|file| file.has_curves()).collect();
+    if files.is_empty() {
+        return Err(MergeError::NoCurves);
+    }
+
+    let mut merged = merged_header(&files, header)?;
+
+    for file in files {
+        for curve in &file.curves {
+            let Some(existing) = merged.curves.iter().position(|c| c.name == curve.name) else {
+                merged.curves.push(curve.clone());
+                continue;
+            };
+
+            match collision {
+                Collision::Error => {
+                    return Err(MergeError::NameCollision {
+                        name: curve.name.clone(),
+                    });
+                }
+                Collision::Rename => {
+                    let name = (1..)
+                        .map(|i| numbered(&curve.name, i))
+                        .find(|name| merged.curve(name).is_none())
+                        .unwrap_or_default();
+
+                    merged.curves.push(Curve {
+                        name,
+                        ..curve.clone()
+                    });
+                }
+                Collision::Skip => {}
+                Collision::Replace => merged.curves[existing] = curve.clone(),
+            }
+        }
+    }
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::ControlPoint;
+    use crate::testing::one_curve;
+
+    /// A file with one curve called `name`, whose first node is at `(x, 0)`.
+    fn file(name: &str, x: i16, frame_count: u16, is_looped: bool) -> MNEBFile {
+        let curve = Curve::new(name, vec![ControlPoint::new(x, 0, 0, 0)], Vec::new());
+        MNEBFile::new(vec![curve], frame_count, is_looped)
+    }
+
+    fn names(mneb_file: &MNEBFile) -> Vec<&str> {
+        mneb_file.curves.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn collisions_are_errors_by_default() {
+        let files = [file("a", 1, 60, true), file("a", 2, 60, true)];
+        let error = merge(&files, Collision::Error, HeaderPolicy::Strict).unwrap_err();
+        assert!(matches!(error, MergeError::NameCollision { name } if name == "a"));
+    }
+
+    #[test]
+    fn renamed_collisions_get_the_next_free_number() {
+        let files = [
+            file("a", 1, 60, true),
+            file("a_1", 2, 60, true),
+            file("a", 3, 60, true),
+        ];
+        let merged = merge(&files, Collision::Rename, HeaderPolicy::Strict).unwrap();
+        assert_eq!(names(&merged), ["a", "a_1", "a_2"]);
+        assert_eq!(merged.curves[2].control_points[0].x, 3);
+    }
+
+    #[test]
+    fn renamed_collisions_fit_in_a_curve_name() {
+        // 31 bytes, so the cut falls in the middle of an `é`
+        let long = format!("a{}", "é".repeat(15));
+        let files = [file(&long, 1, 60, true), file(&long, 2, 60, true)];
+        let merged = merge(&files, Collision::Rename, HeaderPolicy::Strict).unwrap();
+
+        let renamed = &merged.curves[1].name;
+        assert_eq!(*renamed, format!("a{}_1", "é".repeat(14)));
+        assert!(renamed.len() <= CURVE_NAME_SIZE);
+        merged.to_bytes().unwrap();
+    }
+
+    #[test]
+    fn skipped_collisions_keep_the_earlier_curve() {
+        let files = [
+            file("a", 1, 60, true),
+            file("b", 2, 60, true),
+            file("a", 3, 60, true),
+        ];
+        let merged = merge(&files, Collision::Skip, HeaderPolicy::Strict).unwrap();
+        assert_eq!(names(&merged), ["a", "b"]);
+        assert_eq!(merged.curves[0].control_points[0].x, 1);
+    }
+
+    #[test]
+    fn replaced_collisions_keep_the_later_curve_in_place() {
+        let files = [
+            file("a", 1, 60, true),
+            file("b", 2, 60, true),
+            file("a", 3, 60, true),
+        ];
+        let merged = merge(&files, Collision::Replace, HeaderPolicy::Strict).unwrap();
+        assert_eq!(names(&merged), ["a", "b"]);
+        assert_eq!(merged.curves[0].control_points[0].x, 3);
+    }
+
+    #[test]
+    fn strict_headers_must_agree() {
+        let files = [file("a", 1, 60, true), file("b", 2, 90, true)];
+        let error = merge(&files, Collision::Error, HeaderPolicy::Strict).unwrap_err();
+        assert!(matches!(
+            error,
+            MergeError::HeaderMismatch { field: "frame_count", first, other }
+                if first == "60" && other == "90"
+        ));
+
+        let files = [file("a", 1, 60, true), file("b", 2, 60, false)];
+        let error = merge(&files, Collision::Error, HeaderPolicy::Strict).unwrap_err();
+        assert!(matches!(
+            error,
+            MergeError::HeaderMismatch {
+                field: "is_looped",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn first_header_wins() {
+        let files = [file("a", 1, 60, false), file("b", 2, 90, true)];
+        let merged = merge(&files, Collision::Error, HeaderPolicy::First).unwrap();
+        assert_eq!((merged.frame_count, merged.is_looped), (60, false));
+    }
+
+    #[test]
+    fn longest_header_wins() {
+        let files = [
+            file("a", 1, 60, false),
+            file("b", 2, 90, true),
+            file("c", 3, 90, false),
+        ];
+        let merged = merge(&files, Collision::Error, HeaderPolicy::Longest).unwrap();
+        assert_eq!((merged.frame_count, merged.is_looped), (90, true));
+    }
+
+    #[test]
+    fn files_without_curves_are_ignored() {
+        let empty = MNEBFile::new(Vec::new(), 30, false);
+        let files = [empty.clone(), file("a", 1, 60, true)];
+        let merged = merge(&files, Collision::Error, HeaderPolicy::Strict).unwrap();
+        assert_eq!((merged.frame_count, names(&merged)), (60, vec!["a"]));
+
+        let error = merge(&[empty], Collision::Error, HeaderPolicy::Strict).unwrap_err();
+        assert!(matches!(error, MergeError::NoCurves));
+    }
+
+    #[test]
+    fn split_and_extract_keep_the_header() {
+        let merged = merge(
+            &[file("a", 1, 60, false), file("b", 2, 60, false)],
+            Collision::Error,
+            HeaderPolicy::Strict,
+        )
+        .unwrap();
+
+        let parts = split(&merged);
+        assert_eq!(parts.iter().map(names).collect::<Vec<_>>(), [["a"], ["b"]]);
+        assert!(
+            parts
+                .iter()
+                .all(|part| part.frame_count == 60 && !part.is_looped)
+        );
+
+        assert_eq!(names(&extract(&merged, &["b", "a"]).unwrap()), ["b", "a"]);
+        assert!(matches!(
+            extract(&one_curve(Vec::new(), 60), &["b"]),
+            Err(MergeError::CurveNotFound { name }) if name == "b"
+        ));
+    }
+}
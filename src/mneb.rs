@@ -1,9 +1,298 @@
-use anyhow::{Result, ensure};
-use byteorder::{BigEndian, ReadBytesExt};
-use serde::Serialize;
-use std::io::{Cursor, Seek};
+use crate::coverage::Span;
+use crate::error::MnebError;
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::io::{Cursor, Write};
+use std::marker::PhantomData;
+
+type Result<T> = std::result::Result<T, MnebError>;
+
+const HEADER_SIZE: u32 = 0x18;
+pub(crate) const CURVE_NAME_SIZE: usize = 0x20;
+pub(crate) const DEMO_OPTION_SET_NAME_SIZE: usize = 0x20;
+pub(crate) const DEMO_OPTION_NAME_SIZE: usize = 0x10;
+
+/// Deserializes a fixed-size byte array from a sequence of any length, since serde only
+/// supports arrays of up to 32 elements.
+fn deserialize_byte_array<'de, D, const N: usize>(
+    deserializer: D,
+) -> std::result::Result<[u8; N], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = Vec::<u8>::deserialize(deserializer)?;
+    let len = bytes.len();
+    bytes.try_into().map_err(|_| {
+        serde::de::Error::invalid_length(len, &format!("an array of {} bytes", N).as_str())
+    })
+}
+
+/// A bounds-checked reader over a whole file. Errors are tagged with the offset they occurred
+/// at and the struct being parsed, which is tracked in `context`.
+///
+/// When tracing, every read is recorded as a [`Span`] labeled with `scope` and `field`.
+struct Reader<'a, B: ByteOrder> {
+    raw: &'a [u8],
+    position: usize,
+    context: &'static str,
+    spans: Option<Vec<Span>>,
+    /// What's being read, such as `curves[0].control_points[1]`. Only kept when tracing.
+    scope: String,
+    /// The name of the next field to be read.
+    field: &'static str,
+    /// The total size of every table read so far. Tables in a well-formed file don't overlap,
+    /// so this can't exceed the file size, which stops offsets that are reused many times from
+    /// allocating far more than the file holds.
+    table_bytes: usize,
+    byte_order: PhantomData<B>,
+}
+
+impl<'a, B: ByteOrder> Reader<'a, B> {
+    fn new(raw: &'a [u8]) -> Self {
+        Self {
+            raw,
+            position: 0,
+            context: "MNCH header",
+            spans: None,
+            scope: String::new(),
+            field: "",
+            table_bytes: 0,
+            byte_order: PhantomData,
+        }
+    }
+
+    /// Starts recording the spans of everything that's read.
+    fn traced(mut self) -> Self {
+        self.spans = Some(Vec::new());
+        self
+    }
+
+    /// Sets the scope that spans are labeled with. `scope` is only called when tracing.
+    fn scope(&mut self, scope: impl FnOnce() -> String) {
+        if self.spans.is_some() {
+            self.scope = scope();
+        }
+    }
+
+    /// Names the next field to be read.
+    fn field(&mut self, name: &'static str) -> &mut Self {
+        self.field = name;
+        self
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Moves to `position`. Positions past the end are only reported once something is read.
+    fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    fn available(&self) -> usize {
+        self.raw.len().saturating_sub(self.position)
+    }
+
+    /// Fails if `count` entries of `entry_size` bytes can't fit in the rest of the file.
+    fn check_table(&self, count: usize, entry_size: usize) -> Result<()> {
+        let needed = count.saturating_mul(entry_size);
+        if needed > self.available() {
+            return Err(MnebError::TruncatedBlock {
+                context: self.context,
+                offset: self.position as u64,
+                needed: needed as u64,
+                available: self.available() as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks a table of `count` entries of `entry_size` bytes like [`Reader::check_table`],
+    /// and adds it to the total size of tables read, failing if that grows past the file size.
+    fn table(&mut self, count: usize, entry_size: usize) -> Result<()> {
+        self.check_table(count, entry_size)?;
+
+        self.table_bytes = self
+            .table_bytes
+            .saturating_add(count.saturating_mul(entry_size));
+        if self.table_bytes > self.raw.len() {
+            return Err(MnebError::OverlappingTables {
+                context: self.context,
+                offset: self.position as u64,
+                total: self.table_bytes as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn bytes(&mut self, size: usize) -> Result<&'a [u8]> {
+        self.check_table(1, size)?;
+        let bytes = &self.raw[self.position..self.position + size];
+
+        if let Some(spans) = &mut self.spans
+            && size > 0
+        {
+            let label = match (self.scope.is_empty(), self.field.is_empty()) {
+                (_, true) => self.scope.clone(),
+                (true, false) => self.field.to_string(),
+                (false, false) => format!("{}.{}", self.scope, self.field),
+            };
+
+            spans.push(Span {
+                start: self.position,
+                len: size,
+                label,
+            });
+        }
+
+        self.field = "";
+        self.position += size;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn skip(&mut self, size: usize) -> Result<()> {
+        self.bytes(size).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(B::read_u16(self.bytes(2)?))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(B::read_i16(self.bytes(2)?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(B::read_u32(self.bytes(4)?))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(B::read_f32(self.bytes(4)?))
+    }
+
+    /// Reads a block magic, which is stored as a `u32`.
+    fn magic(&mut self, expected: &[u8; 4]) -> Result<()> {
+        let offset = self.position as u64;
+        let found = self.u32()?.to_be_bytes();
+        if &found != expected {
+            return Err(MnebError::BadMagic {
+                context: self.context,
+                offset,
+                expected: *expected,
+                found,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reads an absolute offset, which must point inside the file.
+    fn offset(&mut self) -> Result<usize> {
+        let offset = self.position as u64;
+        let target = self.u32()? as usize;
+        if target >= self.raw.len() {
+            return Err(MnebError::OffsetOutOfRange {
+                context: self.context,
+                offset,
+                target: target as u64,
+            });
+        }
+
+        Ok(target)
+    }
+
+    /// Reads a `u32` entry count for a table of `entry_size` byte entries that follows it.
+    fn count(&mut self, entry_size: usize) -> Result<usize> {
+        let count = self.u32()? as usize;
+        self.table(count, entry_size)?;
+        Ok(count)
+    }
 
-#[derive(Default, Debug, Serialize)]
+    fn string(&mut self, size: usize) -> Result<String> {
+        let offset = self.position as u64;
+        let bytes = self.bytes(size)?.to_vec();
+        String::from_utf8(bytes).map_err(|source| MnebError::BadString {
+            context: self.context,
+            offset,
+            source,
+        })
+    }
+
+    /// Reads a zero-padded name field of `size` bytes.
+    fn name(&mut self, size: usize) -> Result<String> {
+        let offset = self.position as u64;
+        let mut bytes = self.bytes(size)?.to_vec();
+        bytes.retain(|b| *b != 0);
+        String::from_utf8(bytes).map_err(|source| MnebError::BadString {
+            context: self.context,
+            offset,
+            source,
+        })
+    }
+}
+
+/// Writes `name` into a zero-padded field of `size` bytes.
+fn write_name(
+    cursor: &mut Cursor<Vec<u8>>,
+    name: &str,
+    size: usize,
+    context: &'static str,
+) -> Result<()> {
+    let bytes = name.as_bytes();
+    if bytes.len() > size {
+        return Err(MnebError::NameTooLong {
+            context,
+            name: name.to_string(),
+            size,
+        });
+    }
+
+    cursor.write_all(bytes)?;
+    cursor.write_all(&vec![0u8; size - bytes.len()])?;
+    Ok(())
+}
+
+/// Writes `value` as a `u32` at `offset`, then returns to the end of the buffer.
+fn patch_u32<B: ByteOrder>(cursor: &mut Cursor<Vec<u8>>, offset: u64, value: u32) -> Result<()> {
+    let end = cursor.position();
+    cursor.set_position(offset);
+    cursor.write_u32::<B>(value)?;
+    cursor.set_position(end);
+    Ok(())
+}
+
+/// Writes a block magic as a `u32`, so it is byte-swapped in little endian files.
+fn write_magic<B: ByteOrder>(cursor: &mut Cursor<Vec<u8>>, magic: &[u8; 4]) -> Result<()> {
+    cursor.write_u32::<B>(u32::from_be_bytes(*magic))?;
+    Ok(())
+}
+
+/// Points the offset stored at `offset` to the current position.
+fn patch_offset<B: ByteOrder>(cursor: &mut Cursor<Vec<u8>>, offset: u64) -> Result<()> {
+    let position = cursor.position() as u32;
+    patch_u32::<B>(cursor, offset, position)
+}
+
+/// Fills in the size of the block that starts at `start`, ending at the current position.
+fn patch_block_size<B: ByteOrder>(cursor: &mut Cursor<Vec<u8>>, start: u64) -> Result<()> {
+    let size = (cursor.position() - start) as u32;
+    patch_u32::<B>(cursor, start + 4, size)
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A node of a curve at rest, before any key frames are applied.
 pub struct ControlPoint {
     pub x: i16,
     pub y: i16,
@@ -12,11 +301,21 @@ pub struct ControlPoint {
 }
 
 impl ControlPoint {
-    fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self> {
-        let x = cursor.read_i16::<BigEndian>()?;
-        let y = cursor.read_i16::<BigEndian>()?;
-        let z = cursor.read_i16::<BigEndian>()?;
-        let texture_index = cursor.read_u16::<BigEndian>()?;
+    pub fn new(x: i16, y: i16, z: i16, texture_index: u16) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            texture_index,
+        }
+    }
+
+    fn from_bytes<B: ByteOrder>(reader: &mut Reader<B>) -> Result<Self> {
+        reader.context = "ControlPoint";
+        let x = reader.field("x").i16()?;
+        let y = reader.field("y").i16()?;
+        let z = reader.field("z").i16()?;
+        let texture_index = reader.field("texture_index").u16()?;
 
         Ok(Self {
             x,
@@ -25,9 +324,18 @@ impl ControlPoint {
             texture_index,
         })
     }
+
+    fn to_bytes<B: ByteOrder>(&self, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+        cursor.write_i16::<B>(self.x)?;
+        cursor.write_i16::<B>(self.y)?;
+        cursor.write_i16::<B>(self.z)?;
+        cursor.write_u16::<B>(self.texture_index)?;
+        Ok(())
+    }
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The position of a node at a given frame.
 pub struct KeyFrame {
     pub frame: u16,
     pub is_active: bool,
@@ -36,12 +344,22 @@ pub struct KeyFrame {
 }
 
 impl KeyFrame {
-    fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self> {
-        let frame = cursor.read_u16::<BigEndian>()?;
-        let is_active = cursor.read_u8()? != 0;
-        let _ = cursor.seek_relative(1);
-        let x = cursor.read_i16::<BigEndian>()?;
-        let y = cursor.read_i16::<BigEndian>()?;
+    pub fn new(frame: u16, is_active: bool, x: i16, y: i16) -> Self {
+        Self {
+            frame,
+            is_active,
+            x,
+            y,
+        }
+    }
+
+    fn from_bytes<B: ByteOrder>(reader: &mut Reader<B>) -> Result<Self> {
+        reader.context = "KeyFrame";
+        let frame = reader.field("frame").u16()?;
+        let is_active = reader.field("is_active").u8()? != 0;
+        reader.field("padding").skip(1)?;
+        let x = reader.field("x").i16()?;
+        let y = reader.field("y").i16()?;
 
         Ok(Self {
             frame,
@@ -50,23 +368,45 @@ impl KeyFrame {
             y,
         })
     }
+
+    fn to_bytes<B: ByteOrder>(&self, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+        cursor.write_u16::<B>(self.frame)?;
+        cursor.write_u8(self.is_active as u8)?;
+        cursor.write_u8(0)?;
+        cursor.write_i16::<B>(self.x)?;
+        cursor.write_i16::<B>(self.y)?;
+        Ok(())
+    }
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The key frames that move one node of a curve.
 pub struct KeyFrameSet {
+    /// The index of the animated node in [`Curve::control_points`].
     pub node_index: u16,
     pub key_frames: Vec<KeyFrame>,
 }
 
 impl KeyFrameSet {
-    fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self> {
-        let node_index = cursor.read_u16::<BigEndian>()?;
-        let num_key_frames = cursor.read_u16::<BigEndian>()?;
+    pub fn new(node_index: u16, key_frames: Vec<KeyFrame>) -> Self {
+        Self {
+            node_index,
+            key_frames,
+        }
+    }
 
-        let mut key_frames: Vec<KeyFrame> = Vec::with_capacity(num_key_frames as usize);
+    fn from_bytes<B: ByteOrder>(reader: &mut Reader<B>) -> Result<Self> {
+        reader.context = "KeyFrameSet";
+        let node_index = reader.field("node_index").u16()?;
+        let num_key_frames = reader.field("num_key_frames").u16()? as usize;
+        reader.table(num_key_frames, 8)?;
 
-        for _ in 0..num_key_frames {
-            key_frames.push(KeyFrame::from_bytes(cursor)?);
+        let mut key_frames: Vec<KeyFrame> = Vec::with_capacity(num_key_frames);
+
+        let scope = reader.scope.clone();
+        for i in 0..num_key_frames {
+            reader.scope(|| format!("{}.key_frames[{}]", scope, i));
+            key_frames.push(KeyFrame::from_bytes(reader)?);
         }
 
         Ok(Self {
@@ -74,9 +414,28 @@ impl KeyFrameSet {
             key_frames,
         })
     }
+
+    fn to_bytes<B: ByteOrder>(&self, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+        if self.key_frames.len() > u16::MAX as usize {
+            return Err(MnebError::TooManyEntries {
+                context: "KeyFrameSet",
+                count: self.key_frames.len(),
+            });
+        }
+
+        cursor.write_u16::<B>(self.node_index)?;
+        cursor.write_u16::<B>(self.key_frames.len() as u16)?;
+
+        for key_frame in &self.key_frames {
+            key_frame.to_bytes::<B>(cursor)?;
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A curve stored in an `MNCN` block.
 pub struct Curve {
     pub name: String,
     pub control_points: Vec<ControlPoint>,
@@ -84,7 +443,10 @@ pub struct Curve {
     pub key_frame_sets: Vec<KeyFrameSet>,
 
     /* unknown fields */
-    #[serde(serialize_with = "<[_]>::serialize")]
+    #[serde(
+        serialize_with = "<[_]>::serialize",
+        deserialize_with = "deserialize_byte_array"
+    )]
     pub unk_28: [u8; 0x64],
     pub unk_8c: f32,
     pub unk_90: u32,
@@ -114,13 +476,128 @@ impl Default for Curve {
     }
 }
 
-#[derive(Default, Debug, Serialize)]
+impl Curve {
+    /// Creates a curve with no key frames. Unknown fields are zeroed.
+    pub fn new(
+        name: impl Into<String>,
+        control_points: Vec<ControlPoint>,
+        knots: Vec<f32>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            control_points,
+            knots,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the key frames that move the node at `node_index`, if there are any.
+    pub fn key_frame_set(&self, node_index: u16) -> Option<&KeyFrameSet> {
+        self.key_frame_sets
+            .iter()
+            .find(|set| set.node_index == node_index)
+    }
+
+    pub fn key_frame_set_mut(&mut self, node_index: u16) -> Option<&mut KeyFrameSet> {
+        self.key_frame_sets
+            .iter_mut()
+            .find(|set| set.node_index == node_index)
+    }
+
+    /// Writes an `MNCN` block, including the tables it points to.
+    fn to_bytes<B: ByteOrder>(&self, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+        let start = cursor.position();
+
+        write_magic::<B>(cursor, b"MNCN")?;
+        cursor.write_u32::<B>(0)?; // block size
+
+        write_name(cursor, &self.name, CURVE_NAME_SIZE, "Curve")?;
+        cursor.write_all(&self.unk_28)?;
+        cursor.write_f32::<B>(self.unk_8c)?;
+        cursor.write_u32::<B>(self.unk_90)?;
+        cursor.write_u32::<B>(self.unk_94)?;
+        cursor.write_u32::<B>(self.unk_98)?;
+
+        // table offsets, filled in once the tables are written
+        let control_point_table_pos = cursor.position();
+        cursor.write_u32::<B>(0)?;
+        let knot_table_pos = cursor.position();
+        cursor.write_u32::<B>(0)?;
+        let key_frame_info_pos = cursor.position();
+        cursor.write_u32::<B>(0)?;
+
+        for value in &self.unk_a8 {
+            cursor.write_f32::<B>(*value)?;
+        }
+        cursor.write_all(&self.unk_b8)?;
+
+        // control points
+        patch_offset::<B>(cursor, control_point_table_pos)?;
+        cursor.write_u32::<B>(self.control_points.len() as u32)?;
+        for control_point in &self.control_points {
+            control_point.to_bytes::<B>(cursor)?;
+        }
+
+        // knots
+        patch_offset::<B>(cursor, knot_table_pos)?;
+        cursor.write_u32::<B>(self.knots.len() as u32)?;
+        for knot in &self.knots {
+            cursor.write_f32::<B>(*knot)?;
+        }
+
+        // key frame info, which only holds the offset to the key frame table
+        patch_offset::<B>(cursor, key_frame_info_pos)?;
+        let key_frame_table_offset = cursor.position() as u32 + 4;
+        cursor.write_u32::<B>(key_frame_table_offset)?;
+
+        // key frame table
+        cursor.write_u32::<B>(self.key_frame_sets.len() as u32)?;
+        let offsets_pos = cursor.position();
+        for _ in &self.key_frame_sets {
+            cursor.write_u32::<B>(0)?;
+        }
+
+        for (i, key_frame_set) in self.key_frame_sets.iter().enumerate() {
+            patch_offset::<B>(cursor, offsets_pos + i as u64 * 4)?;
+            key_frame_set.to_bytes::<B>(cursor)?;
+        }
+
+        patch_block_size::<B>(cursor, start)?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A named string value in a demo option set.
 pub struct DemoOption {
     pub name: String,
     pub value: String,
 }
 
-#[derive(Default, Debug, Serialize)]
+impl DemoOption {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
+    fn to_bytes<B: ByteOrder>(&self, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+        write_name(cursor, &self.name, DEMO_OPTION_NAME_SIZE, "DemoOption")?;
+        cursor.write_u32::<B>(self.value.len() as u32)?;
+        cursor.write_all(self.value.as_bytes())?;
+
+        // keep the next entry aligned
+        while !cursor.position().is_multiple_of(4) {
+            cursor.write_u8(0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A named group of demo options, stored in an `MNDD` block.
 pub struct DemoOptionSet {
     pub name: String,
     pub demo_options: Vec<DemoOption>,
@@ -129,108 +606,288 @@ pub struct DemoOptionSet {
     pub unk_20: [u8; 0x20],
 }
 
-#[derive(Default, Debug, Serialize)]
+impl DemoOptionSet {
+    /// Creates a demo option set. Unknown fields are zeroed.
+    pub fn new(name: impl Into<String>, demo_options: Vec<DemoOption>) -> Self {
+        Self {
+            name: name.into(),
+            demo_options,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the value of the option called `name`, if there is one.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.demo_options
+            .iter()
+            .find(|option| option.name == name)
+            .map(|option| option.value.as_str())
+    }
+
+    fn to_bytes<B: ByteOrder>(&self, cursor: &mut Cursor<Vec<u8>>) -> Result<()> {
+        write_name(
+            cursor,
+            &self.name,
+            DEMO_OPTION_SET_NAME_SIZE,
+            "DemoOptionSet",
+        )?;
+        cursor.write_all(&self.unk_20)?;
+        cursor.write_u32::<B>(self.demo_options.len() as u32)?;
+
+        let offsets_pos = cursor.position();
+        for _ in &self.demo_options {
+            cursor.write_u32::<B>(0)?;
+        }
+
+        for (i, demo_option) in self.demo_options.iter().enumerate() {
+            patch_offset::<B>(cursor, offsets_pos + i as u64 * 4)?;
+            demo_option.to_bytes::<B>(cursor)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The bytes a file was parsed from, kept so that it can be written back exactly as it was read.
+/// Always compares equal, so files are compared by what they hold rather than by where they
+/// came from.
+#[derive(Clone)]
+pub(crate) struct Source(Vec<u8>);
+
+impl PartialEq for Source {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl std::fmt::Debug for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Source({} bytes)", self.0.len())
+    }
+}
+
+/// The byte order of a file. The Wii release is big endian, while the 3DS release is little
+/// endian.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Endian {
+    #[default]
+    Big,
+    Little,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// An MNEB file, which holds either curves or demo option sets.
 pub struct MNEBFile {
+    /// If this is empty, the file holds [`MNEBFile::demo_option_sets`] instead.
     pub curves: Vec<Curve>,
     pub demo_option_sets: Vec<DemoOptionSet>,
+    /// Missing from JSON written by older versions, which only read big endian files.
+    #[serde(default)]
+    pub endian: Endian,
     pub lock: u32,
     pub frame_count: u16,
     pub is_looped: bool,
 
     /* unknown fields */
     pub _unk_8: u32,
+
+    /// The bytes this file was parsed from, if it was parsed.
+    #[serde(skip)]
+    pub(crate) source: Option<Source>,
 }
 
 impl MNEBFile {
+    /// Creates a big endian file holding `curves`.
+    pub fn new(curves: Vec<Curve>, frame_count: u16, is_looped: bool) -> Self {
+        Self {
+            curves,
+            frame_count,
+            is_looped,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a big endian file holding demo data.
+    pub fn with_demo_option_sets(demo_option_sets: Vec<DemoOptionSet>) -> Self {
+        Self {
+            demo_option_sets,
+            ..Default::default()
+        }
+    }
+
     pub fn has_curves(&self) -> bool {
         !self.curves.is_empty()
     }
 
-    pub fn from_bytes(raw: &[u8]) -> Result<Self, anyhow::Error> {
-        let mut c = Cursor::new(raw);
+    /// Returns the curve called `name`, if there is one.
+    pub fn curve(&self, name: &str) -> Option<&Curve> {
+        self.curves.iter().find(|curve| curve.name == name)
+    }
 
-        let header_magic = c.read_u32::<BigEndian>()?.to_be_bytes();
-        ensure!(&header_magic == b"MNCH", "Invalid file header.");
+    pub fn curve_mut(&mut self, name: &str) -> Option<&mut Curve> {
+        self.curves.iter_mut().find(|curve| curve.name == name)
+    }
 
-        let data_offset = c.read_u32::<BigEndian>()?;
-        let unk_8 = c.read_u32::<BigEndian>()?;
-        let num_curves = c.read_u32::<BigEndian>()?;
-        let lock = c.read_u32::<BigEndian>()?;
-        let frame_count = c.read_u16::<BigEndian>()?;
-        let is_looped = c.read_u8()? != 0;
-        let _ = c.seek_relative(1);
+    /// Serializes the file back into the layout read by [`MNEBFile::from_bytes`], using the
+    /// byte order in [`MNEBFile::endian`].
+    ///
+    /// A file parsed by [`MNEBFile::from_bytes`] that hasn't been modified is written back
+    /// byte for byte, including its offsets and any bytes the parser never reads. Otherwise,
+    /// all offsets and block sizes are recomputed, with each block's tables written one after
+    /// another, and unread bytes are dropped.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let bytes = self.layout()?;
+
+        if let Some(Source(raw)) = &self.source
+            && Self::from_bytes(raw)
+                .and_then(|original| original.layout())
+                .is_ok_and(|original| original == bytes)
+        {
+            return Ok(raw.clone());
+        }
 
-        let mut demo_option_sets: Vec<DemoOptionSet> = Vec::new();
-        let mut curves: Vec<Curve> = Vec::with_capacity(num_curves as usize);
-        // check block type
+        Ok(bytes)
+    }
 
-        if num_curves == 0 {
+    /// Lays the file out from scratch, ignoring the bytes it was parsed from.
+    fn layout(&self) -> Result<Vec<u8>> {
+        match self.endian {
+            Endian::Big => self.to_bytes_with::<BigEndian>(),
+            Endian::Little => self.to_bytes_with::<LittleEndian>(),
+        }
+    }
+
+    fn to_bytes_with<B: ByteOrder>(&self) -> Result<Vec<u8>> {
+        let mut c = Cursor::new(Vec::new());
+
+        write_magic::<B>(&mut c, b"MNCH")?;
+        c.write_u32::<B>(HEADER_SIZE)?;
+        c.write_u32::<B>(self._unk_8)?;
+        c.write_u32::<B>(self.curves.len() as u32)?;
+        c.write_u32::<B>(self.lock)?;
+        c.write_u16::<B>(self.frame_count)?;
+        c.write_u8(self.is_looped as u8)?;
+        c.write_u8(0)?;
+
+        if self.curves.is_empty() {
             // demo data
-            let magic = c.read_u32::<BigEndian>()?.to_be_bytes();
-            ensure!(
-                &magic == b"MNDD",
-                format!("Invalid demo data header at offset {:X}", c.position() - 4)
-            );
-            let _ = c.read_u32::<BigEndian>()?; // block size, but not relevant
-            let num_demo_option_sets = c.read_u32::<BigEndian>()?;
+            let start = c.position();
+            write_magic::<B>(&mut c, b"MNDD")?;
+            c.write_u32::<B>(0)?; // block size
+            c.write_u32::<B>(self.demo_option_sets.len() as u32)?;
+
+            let offsets_pos = c.position();
+            for _ in &self.demo_option_sets {
+                c.write_u32::<B>(0)?;
+            }
 
-            for _ in 0..num_demo_option_sets {
-                let cur_pos = c.position();
+            for (i, demo_option_set) in self.demo_option_sets.iter().enumerate() {
+                patch_offset::<B>(&mut c, offsets_pos + i as u64 * 4)?;
+                demo_option_set.to_bytes::<B>(&mut c)?;
+            }
 
-                // read demo option set
-                let offset = c.read_u32::<BigEndian>()?;
-                c.set_position(offset as u64);
+            patch_block_size::<B>(&mut c, start)?;
+        } else {
+            for curve in &self.curves {
+                curve.to_bytes::<B>(&mut c)?;
+            }
+        }
 
-                let name = {
-                    let pos = c.position() as usize;
-                    let _ = c.seek_relative(0x20);
+        Ok(c.into_inner())
+    }
 
-                    let raw = c.get_ref();
-                    let mut name_vec = raw[pos..pos + 0x20].to_vec();
-                    name_vec.retain(|b| *b != 0);
-                    String::from_utf8(name_vec)?
-                };
+    /// Serializes the file and writes it to `writer`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Parses an MNEB file, detecting its byte order from the `MNCH` magic.
+    ///
+    /// Malformed input never panics; it is reported as an [`MnebError`] instead.
+    pub fn from_bytes(raw: &[u8]) -> Result<Self> {
+        match raw.get(..4) {
+            Some(b"HCNM") => {
+                Self::from_bytes_with(&mut Reader::<LittleEndian>::new(raw), Endian::Little)
+            }
+            _ => Self::from_bytes_with(&mut Reader::<BigEndian>::new(raw), Endian::Big),
+        }
+    }
 
-                let unk_20 = {
-                    let mut temp = [0u8; 0x20];
-                    let pos = c.position() as usize;
-                    let _ = c.seek_relative(0x20);
+    /// Parses an MNEB file like [`MNEBFile::from_bytes`], while recording which bytes each
+    /// field was read from. The spans are returned even if parsing fails, covering everything
+    /// read up to the error.
+    pub fn from_bytes_traced(raw: &[u8]) -> (Result<Self>, Vec<Span>) {
+        match raw.get(..4) {
+            Some(b"HCNM") => {
+                let mut reader = Reader::<LittleEndian>::new(raw).traced();
+                let result = Self::from_bytes_with(&mut reader, Endian::Little);
+                (result, reader.spans.unwrap_or_default())
+            }
+            _ => {
+                let mut reader = Reader::<BigEndian>::new(raw).traced();
+                let result = Self::from_bytes_with(&mut reader, Endian::Big);
+                (result, reader.spans.unwrap_or_default())
+            }
+        }
+    }
 
-                    let raw = c.get_ref();
-                    let v = raw[pos..pos + 0x20].to_vec();
-                    temp.copy_from_slice(&v);
+    fn from_bytes_with<B: ByteOrder>(r: &mut Reader<B>, endian: Endian) -> Result<Self> {
+        r.scope(|| "header".to_string());
+        r.field("magic").magic(b"MNCH")?;
+        let data_offset = r.field("data_offset").offset()?;
+        let unk_8 = r.field("unk_8").u32()?;
+        let num_curves = r.field("num_curves").u32()?;
+        let lock = r.field("lock").u32()?;
+        let frame_count = r.field("frame_count").u16()?;
+        let is_looped = r.field("is_looped").u8()? != 0;
+        r.field("padding").skip(1)?;
 
-                    temp
-                };
+        let mut demo_option_sets: Vec<DemoOptionSet> = Vec::new();
+        let mut curves: Vec<Curve> = Vec::new();
+        // check block type
 
-                let option_count = c.read_u32::<BigEndian>()?;
+        if num_curves == 0 {
+            // demo data
+            r.context = "MNDD block";
+            r.scope(|| "MNDD".to_string());
+            r.field("magic").magic(b"MNDD")?;
+            let _ = r.field("block_size").u32()?; // block size, but not relevant
+            let num_demo_option_sets = r.field("num_demo_option_sets").count(4)?;
 
-                let mut demo_options: Vec<DemoOption> = Vec::with_capacity(option_count as usize);
-                for _ in 0..option_count {
-                    let cur_pos = c.position();
+            for i in 0..num_demo_option_sets {
+                let cur_pos = r.position();
 
-                    let offset = c.read_u32::<BigEndian>()?;
-                    c.set_position(offset as u64);
+                // read demo option set
+                r.context = "MNDD block";
+                r.scope(|| "MNDD".to_string());
+                let offset = r.field("demo_option_set_offset").offset()?;
+                r.set_position(offset);
+
+                r.context = "DemoOptionSet";
+                r.scope(|| format!("demo_option_sets[{}]", i));
+                let name = r.field("name").name(DEMO_OPTION_SET_NAME_SIZE)?;
+                let unk_20 = r.field("unk_20").array::<0x20>()?;
+                let option_count = r.field("num_demo_options").count(4)?;
+
+                let mut demo_options: Vec<DemoOption> = Vec::with_capacity(option_count);
+                for j in 0..option_count {
+                    let cur_pos = r.position();
+
+                    r.context = "DemoOptionSet";
+                    r.scope(|| format!("demo_option_sets[{}]", i));
+                    let offset = r.field("demo_option_offset").offset()?;
+                    r.set_position(offset);
 
                     // read demo options
-                    let name = {
-                        let pos = c.position() as usize;
-                        let _ = c.seek_relative(0x10);
-
-                        let raw = c.get_ref();
-                        let mut name_vec = raw[pos..pos + 0x10].to_vec();
-                        name_vec.retain(|b| *b != 0);
-                        String::from_utf8(name_vec)?
-                    };
-
-                    let num_values = c.read_u32::<BigEndian>()?;
-                    let mut raw_string_bytes: Vec<u8> = Vec::with_capacity(num_values as usize);
-                    let pos = c.position() as usize;
-                    let raw = c.get_ref();
-                    raw_string_bytes.extend_from_slice(&raw[pos..pos + num_values as usize]);
-                    let value = String::from_utf8(raw_string_bytes)?;
+                    r.context = "DemoOption";
+                    r.scope(|| format!("demo_option_sets[{}].demo_options[{}]", i, j));
+                    let name = r.field("name").name(DEMO_OPTION_NAME_SIZE)?;
+                    let num_values = r.field("value_length").u32()? as usize;
+                    r.table(num_values, 1)?;
+                    let value = r.field("value").string(num_values)?;
                     demo_options.push(DemoOption { name, value });
-                    c.set_position(cur_pos + 4);
+                    r.set_position(cur_pos + 4);
                 }
 
                 demo_option_sets.push(DemoOptionSet {
@@ -238,112 +895,104 @@ impl MNEBFile {
                     demo_options,
                     unk_20,
                 });
-                c.set_position(cur_pos + 4);
+                r.set_position(cur_pos + 4);
             }
         } else {
-            c.set_position(data_offset as u64);
-            for _ in 0..num_curves {
-                let start = c.position() as usize;
-                let magic = c.read_u32::<BigEndian>()?.to_be_bytes();
-                ensure!(
-                    &magic == b"MNCN",
-                    format!("Invalid curve header at offset {:X}", c.position() - 4)
-                );
-
-                let block_size = c.read_u32::<BigEndian>()? as usize;
-
-                let offset_to_next = start + block_size;
-
-                let name = {
-                    let pos = c.position() as usize;
-                    let _ = c.seek_relative(0x20);
-
-                    let raw = c.get_ref();
-                    let mut name_vec = raw[pos..pos + 0x20].to_vec();
-                    name_vec.retain(|b| *b != 0);
-
-                    String::from_utf8(name_vec)?
-                };
-
-                let unk_28 = {
-                    let mut temp: [u8; 0x64] = [0u8; 0x64];
-
-                    let pos = c.position() as usize;
-                    let _ = c.seek_relative(0x64);
-
-                    let raw = c.get_ref();
-
-                    temp.copy_from_slice(&raw[pos..pos + 0x64]);
-
-                    temp
-                };
+            r.set_position(data_offset);
+            for i in 0..num_curves {
+                r.context = "MNCN block";
+                r.scope(|| format!("curves[{}]", i));
+                let start = r.position();
+                r.field("magic").magic(b"MNCN")?;
+
+                let block_size_pos = r.position();
+                let block_size = r.field("block_size").u32()? as usize;
+
+                let offset_to_next = start.saturating_add(block_size);
+                if offset_to_next <= start {
+                    // the next block would be this one again
+                    return Err(MnebError::OffsetOutOfRange {
+                        context: r.context,
+                        offset: block_size_pos as u64,
+                        target: offset_to_next as u64,
+                    });
+                }
 
-                let unk_8c = c.read_f32::<BigEndian>()?;
-                let unk_90 = c.read_u32::<BigEndian>()?;
-                let unk_94 = c.read_u32::<BigEndian>()?;
-                let unk_98 = c.read_u32::<BigEndian>()?;
-                let control_point_table_offset = c.read_u32::<BigEndian>()?;
-                let knot_table_offset = c.read_u32::<BigEndian>()?;
-                let key_frame_info_offset = c.read_u32::<BigEndian>()?;
-                let unk_a8 = {
-                    let pos = c.position() as usize;
-                    let _ = c.seek_relative(0x10);
-
-                    let raw = c.get_ref();
-                    let float_slice = bytemuck::cast_slice(&raw[pos..pos + 0x10]);
-                    let mut temp = [0f32; 4];
-                    temp.copy_from_slice(float_slice);
-
-                    temp
-                };
+                r.context = "Curve";
+                let name = r.field("name").name(CURVE_NAME_SIZE)?;
+                let unk_28 = r.field("unk_28").array::<0x64>()?;
+                let unk_8c = r.field("unk_8c").f32()?;
+                let unk_90 = r.field("unk_90").u32()?;
+                let unk_94 = r.field("unk_94").u32()?;
+                let unk_98 = r.field("unk_98").u32()?;
+                let control_point_table_pos = r.position();
+                let control_point_table_offset = r.field("control_point_table_offset").offset()?;
+                let knot_table_offset = r.field("knot_table_offset").offset()?;
+                let key_frame_info_offset = r.field("key_frame_info_offset").offset()?;
+                let unk_a8 = [
+                    r.field("unk_a8[0]").f32()?,
+                    r.field("unk_a8[1]").f32()?,
+                    r.field("unk_a8[2]").f32()?,
+                    r.field("unk_a8[3]").f32()?,
+                ];
 
                 // read any extra data
-                let mut unk_b8 =
-                    Vec::with_capacity((control_point_table_offset as u64 - c.position()) as usize);
-                {
-                    let pos = c.position() as usize;
-                    let raw = c.get_ref();
-                    unk_b8.extend_from_slice(&raw[pos..pos + unk_b8.capacity()])
-                }
+                let extra_size = control_point_table_offset.checked_sub(r.position()).ok_or(
+                    MnebError::OffsetOutOfRange {
+                        context: "Curve",
+                        offset: control_point_table_pos as u64,
+                        target: control_point_table_offset as u64,
+                    },
+                )?;
+                let unk_b8 = r.field("unk_b8").bytes(extra_size)?.to_vec();
 
                 // read control points
-                c.set_position(control_point_table_offset as u64);
-                let num_control_points = c.read_u32::<BigEndian>()?;
-                let mut control_points: Vec<ControlPoint> =
-                    Vec::with_capacity(num_control_points as usize);
-
-                for _ in 0..num_control_points {
-                    control_points.push(ControlPoint::from_bytes(&mut c)?);
+                r.context = "control point table";
+                r.set_position(control_point_table_offset);
+                let num_control_points = r.field("num_control_points").count(8)?;
+                let mut control_points: Vec<ControlPoint> = Vec::with_capacity(num_control_points);
+
+                for j in 0..num_control_points {
+                    r.scope(|| format!("curves[{}].control_points[{}]", i, j));
+                    control_points.push(ControlPoint::from_bytes(r)?);
                 }
 
                 // read knots
-                c.set_position(knot_table_offset as u64);
-                let num_knots = c.read_u32::<BigEndian>()?;
-                let mut knots: Vec<f32> = Vec::with_capacity(num_knots as usize);
-
-                for _ in 0..num_knots {
-                    knots.push(c.read_f32::<BigEndian>()?);
+                r.context = "knot table";
+                r.scope(|| format!("curves[{}]", i));
+                r.set_position(knot_table_offset);
+                let num_knots = r.field("num_knots").count(4)?;
+                let mut knots: Vec<f32> = Vec::with_capacity(num_knots);
+
+                for j in 0..num_knots {
+                    r.scope(|| format!("curves[{}].knots[{}]", i, j));
+                    knots.push(r.f32()?);
                 }
 
                 // read key frame info
-                c.set_position(key_frame_info_offset as u64);
-                let key_frame_table_offset = c.read_u32::<BigEndian>()?;
-                c.set_position(key_frame_table_offset as u64);
+                r.context = "key frame info";
+                r.scope(|| format!("curves[{}]", i));
+                r.set_position(key_frame_info_offset);
+                let key_frame_table_offset = r.field("key_frame_table_offset").offset()?;
+                r.set_position(key_frame_table_offset);
 
-                let num_key_frame_sets = c.read_u32::<BigEndian>()?;
+                r.context = "key frame table";
+                let num_key_frame_sets = r.field("num_key_frame_sets").count(4)?;
 
-                let mut key_frame_sets: Vec<KeyFrameSet> =
-                    Vec::with_capacity(num_key_frame_sets as usize);
+                let mut key_frame_sets: Vec<KeyFrameSet> = Vec::with_capacity(num_key_frame_sets);
 
-                for _ in 0..num_key_frame_sets {
-                    let cur_offset = c.position();
+                for j in 0..num_key_frame_sets {
+                    let cur_offset = r.position();
 
-                    let offset = c.read_u32::<BigEndian>()?;
-                    c.set_position(offset as u64);
+                    r.context = "key frame table";
+                    r.scope(|| format!("curves[{}]", i));
+                    let offset = r.field("key_frame_set_offset").offset()?;
+                    r.set_position(offset);
 
-                    key_frame_sets.push(KeyFrameSet::from_bytes(&mut c)?);
+                    r.scope(|| format!("curves[{}].key_frame_sets[{}]", i, j));
+                    key_frame_sets.push(KeyFrameSet::from_bytes(r)?);
 
-                    c.set_position(cur_offset + 4);
+                    r.set_position(cur_offset + 4);
                 }
 
                 let curve = Curve {
@@ -363,17 +1012,19 @@ impl MNEBFile {
                 curves.push(curve);
 
                 // go to next curve block
-                c.set_position(offset_to_next as u64);
+                r.set_position(offset_to_next);
             }
         }
 
         Ok(Self {
             curves,
             demo_option_sets,
+            endian,
             frame_count,
             _unk_8: unk_8,
             lock,
             is_looped,
+            source: Some(Source(r.raw.to_vec())),
         })
     }
 }
@@ -0,0 +1,492 @@
+//! A CPU rasterizer that draws the same scene as the viewer, without a window or GPU.
+
+use crate::animation::NodeState;
+use crate::mneb::MNEBFile;
+use std::io::Write;
+
+/// An 8-bit RGBA color.
+pub type Color = [u8; 4];
+
+pub const WHITE: Color = [255, 255, 255, 255];
+pub const BLUE: Color = [0, 121, 241, 255];
+pub const GRAY: Color = [130, 130, 130, 255];
+pub const RED: Color = [230, 41, 55, 255];
+pub const GREEN: Color = [0, 228, 48, 255];
+
+/// What part of the scene to show. `zoom` has the same meaning as in the viewer, so the view
+/// can be copied from there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub center: (f32, f32),
+    pub zoom: f32,
+}
+
+impl Camera {
+    /// The smallest camera that shows every curve in `mneb_file` at every frame in `frames`,
+    /// with a small margin.
+    pub fn fit(mneb_file: &MNEBFile, frames: &[f32], width: u32, height: u32) -> Self {
+        let mut min = (f32::MAX, f32::MAX);
+        let mut max = (f32::MIN, f32::MIN);
+
+        for &frame in frames {
+            for curve in &mneb_file.curves {
+                let pose = curve.pose(frame);
+                for (x, y) in pose.outline(&curve.knots) {
+                    min = (min.0.min(x), min.1.min(y));
+                    max = (max.0.max(x), max.1.max(y));
+                }
+            }
+        }
+
+        if min.0 > max.0 {
+            // nothing to show
+            return Self {
+                center: (0.0, 0.0),
+                zoom: 0.001,
+            };
+        }
+
+        let size = ((max.0 - min.0).max(1.0), (max.1 - min.1).max(1.0));
+        let scale = (width as f32 * 0.9 / size.0).min(height as f32 * 0.9 / size.1);
+
+        Self {
+            center: ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0),
+            zoom: scale * 2.0 / height as f32,
+        }
+    }
+}
+
+/// How to render a frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub camera: Camera,
+    /// The thickness of curves, in pixels.
+    pub thickness: f32,
+    /// Whether to draw the control polygon and control points over the curves.
+    pub control_polygon: bool,
+}
+
+/// An RGBA image.
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// Creates a canvas filled with `color`.
+    pub fn new(width: u32, height: u32, color: Color) -> Self {
+        Self {
+            width,
+            height,
+            pixels: color.repeat(width as usize * height as usize),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels of the image, row by row, 4 bytes each.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Blends `color` into the pixel at `x`, `y`, where `coverage` ranges from `0.0` to `1.0`.
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || coverage <= 0.0 {
+            return;
+        }
+
+        let alpha = coverage.min(1.0) * color[3] as f32 / 255.0;
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        for (pixel, &channel) in self.pixels[i..i + 3].iter_mut().zip(&color) {
+            let old = *pixel as f32;
+            *pixel = (old + (channel as f32 - old) * alpha).round() as u8;
+        }
+
+        let old = self.pixels[i + 3] as f32;
+        self.pixels[i + 3] = (old + (255.0 - old) * alpha).round() as u8;
+    }
+
+    /// Fills every pixel whose center is within the shape described by `distance`, which gives
+    /// the distance from a point to the shape's edge. Pixels on the edge are anti-aliased.
+    fn fill(
+        &mut self,
+        min: (f32, f32),
+        max: (f32, f32),
+        color: Color,
+        distance: impl Fn(f32, f32) -> f32,
+    ) {
+        let x_range =
+            (min.0.floor().max(-1.0) as i64)..=(max.0.ceil().min(self.width as f32) as i64);
+        let y_range =
+            (min.1.floor().max(-1.0) as i64)..=(max.1.ceil().min(self.height as f32) as i64);
+
+        for y in y_range {
+            for x in x_range.clone() {
+                let coverage = 0.5 - distance(x as f32 + 0.5, y as f32 + 0.5);
+                self.blend(x, y, color, coverage.clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    /// Draws a line between two points, in pixels.
+    pub fn draw_line(&mut self, a: (f32, f32), b: (f32, f32), thickness: f32, color: Color) {
+        let radius = thickness / 2.0;
+        let min = (a.0.min(b.0) - radius - 1.0, a.1.min(b.1) - radius - 1.0);
+        let max = (a.0.max(b.0) + radius + 1.0, a.1.max(b.1) + radius + 1.0);
+
+        let d = (b.0 - a.0, b.1 - a.1);
+        let length_squared = d.0 * d.0 + d.1 * d.1;
+
+        self.fill(min, max, color, |x, y| {
+            // distance from the pixel to the closest point on the segment
+            let t = if length_squared > 0.0 {
+                (((x - a.0) * d.0 + (y - a.1) * d.1) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            let closest = (a.0 + t * d.0, a.1 + t * d.1);
+            ((x - closest.0).powi(2) + (y - closest.1).powi(2)).sqrt() - radius
+        });
+    }
+
+    /// Draws a filled circle, in pixels.
+    pub fn draw_circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        let min = (center.0 - radius - 1.0, center.1 - radius - 1.0);
+        let max = (center.0 + radius + 1.0, center.1 + radius + 1.0);
+
+        self.fill(min, max, color, |x, y| {
+            ((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt() - radius
+        });
+    }
+
+    /// Draws the outline of a circle, in pixels.
+    pub fn draw_circle_lines(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        let outer = radius + thickness / 2.0;
+        let min = (center.0 - outer - 1.0, center.1 - outer - 1.0);
+        let max = (center.0 + outer + 1.0, center.1 + outer + 1.0);
+
+        self.fill(min, max, color, |x, y| {
+            let distance = ((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt();
+            (distance - radius).abs() - thickness / 2.0
+        });
+    }
+
+    pub fn draw_polyline(&mut self, points: &[(f32, f32)], thickness: f32, color: Color) {
+        for segment in points.windows(2) {
+            self.draw_line(segment[0], segment[1], thickness, color);
+        }
+    }
+
+    /// Encodes the image as a PNG.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()
+    }
+}
+
+/// Draws every curve of `mneb_file` at `current_frame`, the same way the viewer does.
+pub fn render_frame(mneb_file: &MNEBFile, current_frame: f32, options: &RenderOptions) -> Canvas {
+    let mut canvas = Canvas::new(options.width, options.height, WHITE);
+
+    // world space to pixels
+    let scale = options.camera.zoom * options.height as f32 / 2.0;
+    let to_pixels = |(x, y): (f32, f32)| {
+        (
+            (x - options.camera.center.0) * scale + options.width as f32 / 2.0,
+            (y - options.camera.center.1) * scale + options.height as f32 / 2.0,
+        )
+    };
+
+    let thickness = options.thickness;
+
+    for curve in &mneb_file.curves {
+        let pose = curve.pose(current_frame);
+        let positions: Vec<(f32, f32)> = pose.positions.iter().copied().map(to_pixels).collect();
+
+        // draw the curve itself
+        let outline: Vec<(f32, f32)> = pose
+            .outline(&curve.knots)
+            .into_iter()
+            .map(to_pixels)
+            .collect();
+        canvas.draw_polyline(&outline, thickness, BLUE);
+
+        // draw the control polygon and points
+        if options.control_polygon {
+            canvas.draw_polyline(&positions, thickness / 2.0, GRAY);
+
+            for (&pos, state) in positions.iter().zip(&pose.states) {
+                match state {
+                    // not animated
+                    None => canvas.draw_circle(pos, thickness * 1.5, RED),
+                    Some(NodeState::Active(..)) => canvas.draw_circle(pos, thickness * 1.5, GREEN),
+                    Some(NodeState::Inactive) => {
+                        canvas.draw_circle_lines(pos, thickness * 2.0, thickness / 2.0, GRAY)
+                    }
+                }
+            }
+        }
+    }
+
+    canvas
+}
+
+/// The frames to capture for a clip of `mneb_file`, when the animation plays at `framerate`
+/// frames per second and the clip shows `fps` images per second.
+pub fn clip_frames(mneb_file: &MNEBFile, framerate: f32, fps: f32) -> Vec<f32> {
+    let step = framerate / fps;
+    let count = (mneb_file.frame_count as f32 / step).ceil() as usize;
+    (0..count).map(|i| i as f32 * step).collect()
+}
+
+/// Encodes `frames` as an animated GIF showing `fps` images per second. Looped clips repeat
+/// forever, while other clips play once.
+///
+/// Frames are encoded as they're produced, so they don't all need to fit in memory at once.
+pub fn write_gif<W: Write>(
+    frames: impl IntoIterator<Item = Canvas>,
+    fps: f32,
+    looped: bool,
+    writer: W,
+) -> Result<(), gif::EncodingError> {
+    let mut frames = frames.into_iter().peekable();
+    let Some(first) = frames.peek() else {
+        return Ok(());
+    };
+
+    let (Ok(width), Ok(height)) = (u16::try_from(first.width), u16::try_from(first.height)) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "GIF images can't be larger than 65535 pixels",
+        )
+        .into());
+    };
+
+    let mut encoder = gif::Encoder::new(writer, width, height, &[])?;
+    if looped {
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+    }
+
+    // GIF delays are in hundredths of a second
+    let delay = (100.0 / fps).round().max(1.0) as u16;
+    for mut canvas in frames {
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut canvas.pixels, 10);
+        frame.delay = delay;
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(())
+}
+
+/// Encodes `frames` as an animated PNG showing `fps` images per second. Looped clips repeat
+/// forever, while other clips play once.
+///
+/// Frames are encoded as they're produced, like [`write_gif`], but the number of frames must
+/// be known up front.
+pub fn write_apng<W: Write, I>(
+    frames: I,
+    fps: f32,
+    looped: bool,
+    writer: W,
+) -> Result<(), png::EncodingError>
+where
+    I: IntoIterator<Item = Canvas>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let mut frames = frames.into_iter().peekable();
+    let num_frames = frames.len() as u32;
+    let Some(first) = frames.peek() else {
+        return Ok(());
+    };
+
+    let mut encoder = png::Encoder::new(writer, first.width, first.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(num_frames, if looped { 0 } else { 1 })?;
+    // delays are given as a fraction of a second, here in milliseconds
+    encoder.set_frame_delay((1000.0 / fps).round().max(1.0) as u16, 1000)?;
+
+    let mut writer = encoder.write_header()?;
+    for canvas in frames {
+        writer.write_image_data(&canvas.pixels)?;
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::{ControlPoint, Curve, KeyFrame, KeyFrameSet};
+    use crate::spline::clamped_knots;
+
+    /// An L-shaped line from (0, 0) to (100, 0) to (100, 50).
+    fn corner() -> MNEBFile {
+        let points = vec![
+            ControlPoint::new(0, 0, 0, 0),
+            ControlPoint::new(100, 0, 0, 0),
+            ControlPoint::new(100, 50, 0, 0),
+        ];
+        let curve = Curve::new("corner", points, clamped_knots(3, 1));
+        MNEBFile::new(vec![curve], 10, true)
+    }
+
+    fn pixel(canvas: &Canvas, x: u32, y: u32) -> Color {
+        let i = ((y * canvas.width() + x) * 4) as usize;
+        canvas.pixels()[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn fit_leaves_a_margin() {
+        let camera = Camera::fit(&corner(), &[0.0], 200, 100);
+
+        assert_eq!(camera.center, (50.0, 25.0));
+        // the 100 by 50 curve fills 90% of the 200 by 100 image
+        assert!((camera.zoom * 100.0 / 2.0 - 1.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn fit_covers_every_frame() {
+        let mut mneb_file = corner();
+        mneb_file.curves[0].key_frame_sets = vec![KeyFrameSet::new(
+            2,
+            vec![
+                KeyFrame::new(0, true, 100, 50),
+                KeyFrame::new(10, true, 100, 150),
+            ],
+        )];
+
+        let camera = Camera::fit(&mneb_file, &[0.0, 10.0], 100, 100);
+        assert_eq!(camera.center, (50.0, 75.0));
+    }
+
+    #[test]
+    fn fit_with_nothing_to_show() {
+        let camera = Camera::fit(&MNEBFile::default(), &[0.0], 100, 100);
+        assert_eq!(camera.center, (0.0, 0.0));
+    }
+
+    #[test]
+    fn render_matches_the_curve() {
+        let mneb_file = corner();
+        let mut options = RenderOptions {
+            width: 200,
+            height: 100,
+            camera: Camera::fit(&mneb_file, &[0.0], 200, 100),
+            thickness: 3.0,
+            control_polygon: false,
+        };
+        let canvas = render_frame(&mneb_file, 0.0, &options);
+
+        // (0, 0) lands at (10, 5) and (100, 50) at (190, 95)
+        assert_eq!(pixel(&canvas, 100, 5), BLUE);
+        assert_eq!(pixel(&canvas, 190, 50), BLUE);
+        assert_eq!(pixel(&canvas, 100, 50), WHITE);
+        assert_eq!(pixel(&canvas, 10, 95), WHITE);
+
+        // rendering is deterministic, so renders can be compared image to image
+        assert_eq!(
+            render_frame(&mneb_file, 0.0, &options).pixels(),
+            canvas.pixels()
+        );
+
+        // the nodes aren't animated, so they're marked in red
+        options.control_polygon = true;
+        let canvas = render_frame(&mneb_file, 0.0, &options);
+        assert_eq!(pixel(&canvas, 10, 5), RED);
+        assert_eq!(pixel(&canvas, 190, 95), RED);
+    }
+
+    #[test]
+    fn clip_frames_cover_the_animation() {
+        let mneb_file = MNEBFile::new(Vec::new(), 10, true);
+
+        assert_eq!(
+            clip_frames(&mneb_file, 60.0, 60.0),
+            (0..10).map(|i| i as f32).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            clip_frames(&mneb_file, 60.0, 30.0),
+            [0.0, 2.0, 4.0, 6.0, 8.0]
+        );
+        // the last image is shown before the animation ends, rounding the count up
+        assert_eq!(
+            clip_frames(&mneb_file, 60.0, 40.0),
+            [0.0, 1.5, 3.0, 4.5, 6.0, 7.5, 9.0]
+        );
+        assert_eq!(clip_frames(&mneb_file, 30.0, 60.0).len(), 20);
+        assert!(clip_frames(&MNEBFile::default(), 60.0, 60.0).is_empty());
+    }
+
+    fn frames() -> impl ExactSizeIterator<Item = Canvas> {
+        [WHITE, RED, GREEN]
+            .into_iter()
+            .map(|color| Canvas::new(4, 2, color))
+    }
+
+    #[test]
+    fn gifs_only_repeat_when_looped() {
+        for looped in [true, false] {
+            let mut gif = Vec::new();
+            write_gif(frames(), 20.0, looped, &mut gif).unwrap();
+
+            let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+            let mut num_frames = 0;
+            while let Some(frame) = decoder.read_next_frame().unwrap() {
+                assert_eq!((frame.width, frame.height), (4, 2));
+                assert_eq!(frame.delay, 5);
+                num_frames += 1;
+            }
+
+            assert_eq!(num_frames, 3);
+            assert_eq!(decoder.repeat() == gif::Repeat::Infinite, looped);
+        }
+    }
+
+    #[test]
+    fn apngs_play_once_unless_looped() {
+        for (looped, num_plays) in [(true, 0), (false, 1)] {
+            let mut apng = Vec::new();
+            write_apng(frames(), 20.0, looped, &mut apng).unwrap();
+
+            let reader = png::Decoder::new(std::io::Cursor::new(apng))
+                .read_info()
+                .unwrap();
+            let info = reader.info();
+            assert_eq!((info.width, info.height), (4, 2));
+
+            let control = info.animation_control.unwrap();
+            assert_eq!(control.num_frames, 3);
+            assert_eq!(control.num_plays, num_plays);
+        }
+    }
+
+    #[test]
+    fn no_frames_write_nothing() {
+        let mut out = Vec::new();
+        write_gif(std::iter::empty(), 20.0, true, &mut out).unwrap();
+        write_apng(std::iter::empty(), 20.0, true, &mut out).unwrap();
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+//! Changing the timing of animations: trimming them to a range of frames, then stretching and
+//! offsetting every key frame.
+
+use crate::animation::{NodeState, interpolate};
+use crate::error::RetimeError;
+use crate::mneb::{KeyFrame, KeyFrameSet, MNEBFile};
+use std::str::FromStr;
+
+/// What to do when several key frames of a node round to the same frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameCollision {
+    /// Keep the latest key frame, so the node ends up where it was going.
+    #[default]
+    Last,
+    /// Keep the earliest key frame.
+    First,
+    /// Fail with [`RetimeError::Collision`].
+    Error,
+}
+
+impl FromStr for FrameCollision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last" => Ok(Self::Last),
+            "first" => Ok(Self::First),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "expected `last`, `first` or `error`, found `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// How to retime a file. Trimming happens first, then scaling, then offsetting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetimeOptions {
+    /// The first and last frames to keep. The first becomes frame 0.
+    pub range: Option<(u16, u16)>,
+    /// How many times longer the animation should be.
+    pub scale: f32,
+    /// The frame count to stretch the trimmed animation to, instead of `scale`.
+    pub frame_count: Option<u16>,
+    /// How many frames to delay every key frame by, which also lengthens the animation.
+    pub offset: i32,
+    pub collision: FrameCollision,
+}
+
+impl Default for RetimeOptions {
+    fn default() -> Self {
+        Self {
+            range: None,
+            scale: 1.0,
+            frame_count: None,
+            offset: 0,
+            collision: FrameCollision::default(),
+        }
+    }
+}
+
+/// The key frame that puts a node in the state it has at `frame`.
+fn sample(key_frames: &[KeyFrame], frame: u16) -> Option<KeyFrame> {
+    match interpolate(key_frames, frame as f32)? {
+        NodeState::Active(x, y) => Some(KeyFrame::new(
+            frame,
+            true,
+            x.round() as i16,
+            y.round() as i16,
+        )),
+        NodeState::Inactive => Some(KeyFrame::new(frame, false, 0, 0)),
+    }
+}
+
+/// Drops the key frames outside `start..=end` and moves `start` to frame 0. Key frames are added
+/// at either end wherever dropped key frames affected the node within the range.
+fn trim(set: &mut KeyFrameSet, start: u16, end: u16) {
+    let keys = &set.key_frames;
+    let outside = keys.iter().any(|k| k.frame < start || k.frame > end);
+    let after = keys.iter().any(|k| k.frame > end);
+    let has_key_at = |frame: u16| keys.iter().any(|k| k.frame == frame);
+
+    let first = (outside && !has_key_at(start))
+        .then(|| sample(keys, start))
+        .flatten();
+    let last = (after && !has_key_at(end))
+        .then(|| sample(keys, end))
+        .flatten();
+
+    let mut trimmed: Vec<KeyFrame> = first.into_iter().collect();
+    trimmed.extend(
+        keys.iter()
+            .filter(|k| k.frame >= start && k.frame <= end)
+            .cloned(),
+    );
+    trimmed.extend(last);
+
+    for key_frame in &mut trimmed {
+        key_frame.frame -= start;
+    }
+    set.key_frames = trimmed;
+}
+
+/// Maps `frame` to its retimed frame.
+fn retime_frame(frame: u16, scale: f32, offset: i32) -> Result<u16, RetimeError> {
+    let value = frame as f32 * scale + offset as f32;
+    let rounded = value.round();
+    if (0.0..=u16::MAX as f32).contains(&rounded) {
+        Ok(rounded as u16)
+    } else {
+        Err(RetimeError::FrameOutOfRange { frame, value })
+    }
+}
+
+/// Retimes every key frame of `mneb_file` and updates its frame count. If anything fails, the
+/// file is left as it was.
+pub fn retime(mneb_file: &mut MNEBFile, options: &RetimeOptions) -> Result<(), RetimeError> {
+    let mut curves = mneb_file.curves.clone();
+    let mut frame_count = mneb_file.frame_count;
+
+    if let Some((start, end)) = options.range {
+        if start >= end || end > frame_count {
+            return Err(RetimeError::BadRange {
+                start,
+                end,
+                frame_count,
+            });
+        }
+
+        for set in curves.iter_mut().flat_map(|c| &mut c.key_frame_sets) {
+            trim(set, start, end);
+        }
+        frame_count = end - start;
+    }
+
+    let scale = match options.frame_count {
+        Some(target) => target as f32 / frame_count.max(1) as f32,
+        None => options.scale,
+    };
+    if !(scale > 0.0 && scale.is_finite()) {
+        return Err(RetimeError::BadScale(scale));
+    }
+
+    for curve in &mut curves {
+        for set in &mut curve.key_frame_sets {
+            let mut retimed: Vec<KeyFrame> = Vec::with_capacity(set.key_frames.len());
+
+            for key_frame in &set.key_frames {
+                let frame = retime_frame(key_frame.frame, scale, options.offset)?;
+                let key_frame = KeyFrame {
+                    frame,
+                    ..key_frame.clone()
+                };
+
+                match retimed.last_mut() {
+                    Some(previous) if previous.frame == frame => match options.collision {
+                        FrameCollision::Last => *previous = key_frame,
+                        FrameCollision::First => {}
+                        FrameCollision::Error => {
+                            return Err(RetimeError::Collision {
+                                curve: curve.name.clone(),
+                                node_index: set.node_index,
+                                frame,
+                            });
+                        }
+                    },
+                    _ => retimed.push(key_frame),
+                }
+            }
+
+            set.key_frames = retimed;
+        }
+    }
+
+    mneb_file.frame_count = retime_frame(frame_count, scale, options.offset)?;
+    mneb_file.curves = curves;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::one_curve;
+
+    fn key_frames(mneb_file: &MNEBFile) -> &[KeyFrame] {
+        &mneb_file.curves[0].key_frame_sets[0].key_frames
+    }
+
+    fn trimmed(key_frames: Vec<KeyFrame>, start: u16, end: u16) -> MNEBFile {
+        let mut mneb_file = one_curve(key_frames, 100);
+        let options = RetimeOptions {
+            range: Some((start, end)),
+            ..Default::default()
+        };
+        retime(&mut mneb_file, &options).unwrap();
+        mneb_file
+    }
+
+    #[test]
+    fn trim_at_range_ends() {
+        let mneb_file = trimmed(
+            vec![
+                KeyFrame::new(0, true, 0, 0),
+                KeyFrame::new(20, true, 20, 0),
+                KeyFrame::new(40, true, 40, 0),
+                KeyFrame::new(60, true, 60, 0),
+            ],
+            20,
+            40,
+        );
+
+        assert_eq!(mneb_file.frame_count, 20);
+        assert_eq!(
+            key_frames(&mneb_file),
+            [
+                KeyFrame::new(0, true, 20, 0),
+                KeyFrame::new(20, true, 40, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn trim_past_range_ends() {
+        let mneb_file = trimmed(
+            vec![
+                KeyFrame::new(0, true, 0, 0),
+                KeyFrame::new(30, true, 30, 60),
+                KeyFrame::new(60, true, 60, 0),
+            ],
+            15,
+            45,
+        );
+
+        assert_eq!(mneb_file.frame_count, 30);
+        assert_eq!(
+            key_frames(&mneb_file),
+            [
+                KeyFrame::new(0, true, 15, 30),
+                KeyFrame::new(15, true, 30, 60),
+                KeyFrame::new(30, true, 45, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn trim_keeps_inactive_state() {
+        let mneb_file = trimmed(
+            vec![
+                KeyFrame::new(0, false, 0, 0),
+                KeyFrame::new(50, true, 10, 10),
+            ],
+            10,
+            90,
+        );
+
+        assert_eq!(
+            key_frames(&mneb_file),
+            [
+                KeyFrame::new(0, false, 0, 0),
+                KeyFrame::new(40, true, 10, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn trim_past_frame_count() {
+        let mut mneb_file = one_curve(vec![KeyFrame::new(0, true, 0, 0)], 100);
+        let options = RetimeOptions {
+            range: Some((10, 101)),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            retime(&mut mneb_file, &options),
+            Err(RetimeError::BadRange { .. })
+        ));
+    }
+
+    /// Halves key frames at frames 0, 1 and 2, so the ones at 1 and 2 both land on frame 1.
+    fn halved(collision: FrameCollision) -> (MNEBFile, Result<(), RetimeError>) {
+        let mut mneb_file = one_curve(
+            vec![
+                KeyFrame::new(0, true, 0, 0),
+                KeyFrame::new(1, true, 1, 0),
+                KeyFrame::new(2, true, 2, 0),
+            ],
+            4,
+        );
+        let options = RetimeOptions {
+            scale: 0.5,
+            collision,
+            ..Default::default()
+        };
+
+        let result = retime(&mut mneb_file, &options);
+        (mneb_file, result)
+    }
+
+    #[test]
+    fn collision_keeps_last() {
+        let (mneb_file, result) = halved(FrameCollision::Last);
+
+        assert!(result.is_ok());
+        assert_eq!(mneb_file.frame_count, 2);
+        assert_eq!(
+            key_frames(&mneb_file),
+            [KeyFrame::new(0, true, 0, 0), KeyFrame::new(1, true, 2, 0)]
+        );
+    }
+
+    #[test]
+    fn collision_keeps_first() {
+        let (mneb_file, result) = halved(FrameCollision::First);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            key_frames(&mneb_file),
+            [KeyFrame::new(0, true, 0, 0), KeyFrame::new(1, true, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn collision_error_leaves_file_unchanged() {
+        let (mneb_file, result) = halved(FrameCollision::Error);
+
+        assert!(matches!(
+            result,
+            Err(RetimeError::Collision {
+                node_index: 1,
+                frame: 1,
+                ..
+            })
+        ));
+        assert_eq!(mneb_file.frame_count, 4);
+        assert_eq!(key_frames(&mneb_file).len(), 3);
+    }
+
+    #[test]
+    fn offset_before_frame_0() {
+        let mut mneb_file = one_curve(vec![KeyFrame::new(2, true, 0, 0)], 10);
+        let options = RetimeOptions {
+            offset: -3,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            retime(&mut mneb_file, &options),
+            Err(RetimeError::FrameOutOfRange { frame: 2, .. })
+        ));
+    }
+}
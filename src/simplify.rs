@@ -0,0 +1,242 @@
+//! Removing key frames that interpolation between their neighbours already reproduces.
+
+use crate::animation::{NodeState, interpolate};
+use crate::mneb::{KeyFrame, MNEBFile};
+use serde::Serialize;
+
+/// How simplifying changed the key frames of one node.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeReduction {
+    pub curve: String,
+    pub node_index: u16,
+    /// The number of key frames before simplifying.
+    pub before: usize,
+    /// The number of key frames after simplifying.
+    pub after: usize,
+    /// The furthest the node strays from where it used to be on any frame, in pixels.
+    pub max_error: f32,
+}
+
+/// How far the position interpolated between `start` and `end` at `middle.frame` is from
+/// `middle`.
+fn error(start: &KeyFrame, middle: &KeyFrame, end: &KeyFrame) -> f32 {
+    let t = (middle.frame as f32 - start.frame as f32) / (end.frame as f32 - start.frame as f32);
+    let x = start.x as f32 + t * (end.x as f32 - start.x as f32);
+    let y = start.y as f32 + t * (end.y as f32 - start.y as f32);
+    (x - middle.x as f32).hypot(y - middle.y as f32)
+}
+
+/// Marks the key frames between `start` and `end` to keep, splitting at the one that strays
+/// furthest until every removed key frame is within `tolerance` (Ramer-Douglas-Peucker).
+fn mark(key_frames: &[KeyFrame], start: usize, end: usize, tolerance: f32, keep: &mut [bool]) {
+    let furthest = (start + 1..end)
+        .map(|i| {
+            (
+                i,
+                error(&key_frames[start], &key_frames[i], &key_frames[end]),
+            )
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    if let Some((i, error)) = furthest
+        && error > tolerance
+    {
+        keep[i] = true;
+        mark(key_frames, start, i, tolerance, keep);
+        mark(key_frames, i, end, tolerance, keep);
+    }
+}
+
+/// Removes the key frames that interpolating between the remaining ones reproduces within
+/// `tolerance` pixels.
+///
+/// Only runs of active key frames at increasing frames are simplified, so the first and last
+/// key frames, jumps, and the key frames that switch a node off and on are always kept.
+pub fn simplify_key_frames(key_frames: &[KeyFrame], tolerance: f32) -> Vec<KeyFrame> {
+    let mut keep = vec![true; key_frames.len()];
+
+    let mut start = 0;
+    while start < key_frames.len() {
+        // find the end of the run of active key frames starting here
+        let mut end = start;
+        while end + 1 < key_frames.len()
+            && key_frames[start].is_active
+            && key_frames[end + 1].is_active
+            && key_frames[end + 1].frame > key_frames[end].frame
+        {
+            end += 1;
+        }
+
+        if end > start + 1 {
+            keep[start + 1..end].fill(false);
+            mark(key_frames, start, end, tolerance, &mut keep);
+        }
+        start = end.max(start + 1);
+    }
+
+    // a node that's already switched off ignores the position of another inactive key frame
+    for i in 1..key_frames.len().saturating_sub(1) {
+        if !key_frames[i - 1].is_active && !key_frames[i].is_active {
+            keep[i] = false;
+        }
+    }
+
+    key_frames
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(key_frame, _)| key_frame.clone())
+        .collect()
+}
+
+/// The furthest apart the nodes animated by `a` and `b` are on any frame they cover.
+pub fn max_error(a: &[KeyFrame], b: &[KeyFrame]) -> f32 {
+    let frames = a.iter().chain(b).map(|key_frame| key_frame.frame);
+    let (Some(first), Some(last)) = (frames.clone().min(), frames.max()) else {
+        return 0.0;
+    };
+
+    (first..=last)
+        .map(
+            |frame| match (interpolate(a, frame as f32), interpolate(b, frame as f32)) {
+                (Some(NodeState::Active(ax, ay)), Some(NodeState::Active(bx, by))) => {
+                    (ax - bx).hypot(ay - by)
+                }
+                (Some(NodeState::Inactive), Some(NodeState::Inactive)) => 0.0,
+                _ => f32::INFINITY,
+            },
+        )
+        .fold(0.0, f32::max)
+}
+
+/// Simplifies every key frame set of `mneb_file`, and reports how each one changed.
+pub fn simplify(mneb_file: &mut MNEBFile, tolerance: f32) -> Vec<NodeReduction> {
+    let mut reductions = Vec::new();
+
+    for curve in &mut mneb_file.curves {
+        for set in &mut curve.key_frame_sets {
+            let simplified = simplify_key_frames(&set.key_frames, tolerance);
+
+            reductions.push(NodeReduction {
+                curve: curve.name.clone(),
+                node_index: set.node_index,
+                before: set.key_frames.len(),
+                after: simplified.len(),
+                max_error: max_error(&set.key_frames, &simplified),
+            });
+            set.key_frames = simplified;
+        }
+    }
+
+    reductions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::curves;
+
+    /// A key frame on every frame, following a wave.
+    fn wave() -> Vec<KeyFrame> {
+        (0..120)
+            .map(|frame| {
+                let t = frame as f32 / 10.0;
+                KeyFrame::new(
+                    frame,
+                    true,
+                    (t.sin() * 50.0).round() as i16,
+                    (t.cos() * 20.0 + frame as f32).round() as i16,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn error_within_tolerance() {
+        let key_frames = wave();
+
+        for tolerance in [0.0, 0.5, 1.0, 4.0, 20.0] {
+            let simplified = simplify_key_frames(&key_frames, tolerance);
+
+            assert!(simplified.len() < key_frames.len());
+            assert_eq!(simplified.first(), key_frames.first());
+            assert_eq!(simplified.last(), key_frames.last());
+            assert!(max_error(&key_frames, &simplified) <= tolerance);
+        }
+    }
+
+    #[test]
+    fn straight_lines_collapse() {
+        let key_frames: Vec<KeyFrame> = (0..=60)
+            .map(|frame| KeyFrame::new(frame, true, frame as i16 * 2, -(frame as i16)))
+            .collect();
+
+        assert_eq!(
+            simplify_key_frames(&key_frames, 0.0),
+            [
+                KeyFrame::new(0, true, 0, 0),
+                KeyFrame::new(60, true, 120, -60)
+            ]
+        );
+    }
+
+    #[test]
+    fn inactive_after_inactive_is_removed() {
+        let key_frames = vec![
+            KeyFrame::new(0, true, 0, 0),
+            KeyFrame::new(10, true, 10, 0),
+            KeyFrame::new(20, false, 0, 0),
+            KeyFrame::new(21, false, 5, 5),
+            KeyFrame::new(22, false, 0, 0),
+            KeyFrame::new(30, true, 30, 0),
+            KeyFrame::new(40, false, 0, 0),
+            KeyFrame::new(50, false, 0, 0),
+        ];
+        let simplified = simplify_key_frames(&key_frames, 0.0);
+
+        // the switches off and on are kept, as is the last key frame
+        assert_eq!(
+            simplified,
+            [
+                KeyFrame::new(0, true, 0, 0),
+                KeyFrame::new(10, true, 10, 0),
+                KeyFrame::new(20, false, 0, 0),
+                KeyFrame::new(30, true, 30, 0),
+                KeyFrame::new(40, false, 0, 0),
+                KeyFrame::new(50, false, 0, 0),
+            ]
+        );
+        assert_eq!(max_error(&key_frames, &simplified), 0.0);
+    }
+
+    #[test]
+    fn jumps_are_kept() {
+        let key_frames = vec![
+            KeyFrame::new(0, true, 0, 0),
+            KeyFrame::new(10, true, 10, 0),
+            KeyFrame::new(10, true, 100, 0),
+            KeyFrame::new(20, true, 110, 0),
+        ];
+
+        assert_eq!(simplify_key_frames(&key_frames, 1.0), key_frames);
+    }
+
+    #[test]
+    fn report() {
+        let mut mneb_file = curves();
+        let set = &mut mneb_file.curves[0].key_frame_sets[0];
+        set.key_frames = wave();
+        let node_index = set.node_index;
+
+        let reductions = simplify(&mut mneb_file, 1.0);
+        let reduction = &reductions[0];
+
+        assert_eq!(reduction.node_index, node_index);
+        assert_eq!(reduction.before, 120);
+        assert_eq!(
+            reduction.after,
+            mneb_file.curves[0].key_frame_sets[0].key_frames.len()
+        );
+        assert!(reduction.max_error <= 1.0);
+    }
+}
@@ -0,0 +1,200 @@
+//! B-spline evaluation for [`Curve`]s.
+//!
+//! The degree of a curve isn't stored anywhere, so it's inferred from its knot vector, which
+//! holds `control_points + degree + 1` knots. The format has no weights, so every curve is a
+//! non-rational B-spline.
+
+use crate::mneb::Curve;
+
+/// Infers the degree of a B-spline from its control point and knot counts.
+///
+/// Returns `None` if the counts don't describe a B-spline of at least degree 1.
+pub fn degree(num_points: usize, num_knots: usize) -> Option<usize> {
+    let degree = num_knots.checked_sub(num_points + 1)?;
+    (degree >= 1 && degree < num_points).then_some(degree)
+}
+
+/// Evaluates the B-spline with the given control points and knots at `t`, which ranges from
+/// `0.0` at the start of the curve to `1.0` at its end.
+///
+/// Returns `None` if the knots don't match the points; see [`degree`].
+pub fn evaluate(points: &[(f32, f32)], knots: &[f32], t: f32) -> Option<(f32, f32)> {
+    let degree = degree(points.len(), knots.len())?;
+
+    // map t onto the valid part of the knot vector
+    let start = knots[degree];
+    let end = knots[points.len()];
+    let u = start + t.clamp(0.0, 1.0) * (end - start);
+
+    // find the knot span containing u, so that knots[span] <= u < knots[span + 1]
+    let mut span = degree;
+    while span < points.len() - 1 && u >= knots[span + 1] {
+        span += 1;
+    }
+
+    // de Boor's algorithm
+    let mut d: Vec<(f32, f32)> = points[span - degree..=span].to_vec();
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let i = span - degree + j;
+            let denominator = knots[i + degree + 1 - r] - knots[i];
+            let alpha = if denominator != 0.0 {
+                (u - knots[i]) / denominator
+            } else {
+                0.0
+            };
+
+            d[j] = (
+                (1.0 - alpha) * d[j - 1].0 + alpha * d[j].0,
+                (1.0 - alpha) * d[j - 1].1 + alpha * d[j].1,
+            );
+        }
+    }
+
+    Some(d[degree])
+}
+
+/// Samples the B-spline at `segments + 1` evenly spaced parameters, from start to end.
+///
+/// Returns an empty list if the knots don't match the points; see [`degree`].
+pub fn tessellate(points: &[(f32, f32)], knots: &[f32], segments: usize) -> Vec<(f32, f32)> {
+    if degree(points.len(), knots.len()).is_none() {
+        return Vec::new();
+    }
+
+    let segments = segments.max(1);
+    (0..=segments)
+        .filter_map(|i| evaluate(points, knots, i as f32 / segments as f32))
+        .collect()
+}
+
+/// Builds a clamped, uniform knot vector for `num_points` control points, which makes the
+/// B-spline start and end at the first and last points. The degree is lowered if there are
+/// too few points for it.
+///
+/// Returns an empty list if there are fewer than 2 points.
+pub fn clamped_knots(num_points: usize, degree: usize) -> Vec<f32> {
+    if num_points < 2 {
+        return Vec::new();
+    }
+
+    let degree = degree.clamp(1, num_points - 1);
+    let spans = num_points - degree;
+
+    let mut knots = vec![0.0; degree + 1];
+    knots.extend((1..spans).map(|i| i as f32 / spans as f32));
+    knots.extend(std::iter::repeat_n(1.0, degree + 1));
+    knots
+}
+
+/// Builds the knot vector of a B-spline made of `num_segments` Bézier segments of `degree`
+/// joined end to end, which has `num_segments * degree + 1` control points. Each inner knot is
+/// repeated `degree` times, so the curve passes through the point where segments join.
+pub fn bezier_knots(num_segments: usize, degree: usize) -> Vec<f32> {
+    let mut knots = vec![0.0; degree + 1];
+    for i in 1..num_segments {
+        knots.extend(std::iter::repeat_n(i as f32 / num_segments as f32, degree));
+    }
+    knots.extend(std::iter::repeat_n(1.0, degree + 1));
+    knots
+}
+
+impl Curve {
+    /// The degree of the curve, inferred from its knot and control point counts.
+    pub fn degree(&self) -> Option<usize> {
+        degree(self.control_points.len(), self.knots.len())
+    }
+
+    /// The x and y positions of the control points, before any key frames are applied.
+    pub fn rest_positions(&self) -> Vec<(f32, f32)> {
+        self.control_points
+            .iter()
+            .map(|point| (point.x as f32, point.y as f32))
+            .collect()
+    }
+
+    /// Evaluates the curve at rest at `t`, from `0.0` to `1.0`.
+    pub fn evaluate(&self, t: f32) -> Option<(f32, f32)> {
+        evaluate(&self.rest_positions(), &self.knots, t)
+    }
+
+    /// Samples the curve at rest at `segments + 1` points.
+    pub fn tessellate(&self, segments: usize) -> Vec<(f32, f32)> {
+        tessellate(&self.rest_positions(), &self.knots, segments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [(f32, f32); 5] = [
+        (0.0, 0.0),
+        (10.0, 30.0),
+        (20.0, -30.0),
+        (30.0, 30.0),
+        (40.0, 0.0),
+    ];
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4
+    }
+
+    #[test]
+    fn clamped_curves_touch_their_end_points() {
+        for degree in 1..=4 {
+            let knots = clamped_knots(POINTS.len(), degree);
+            assert_eq!(knots.len(), POINTS.len() + degree + 1);
+
+            assert!(close(evaluate(&POINTS, &knots, 0.0).unwrap(), POINTS[0]));
+            assert!(close(evaluate(&POINTS, &knots, 1.0).unwrap(), POINTS[4]));
+
+            // parameters outside the curve are clamped to its ends
+            assert!(close(evaluate(&POINTS, &knots, -1.0).unwrap(), POINTS[0]));
+            assert!(close(evaluate(&POINTS, &knots, 2.0).unwrap(), POINTS[4]));
+        }
+    }
+
+    #[test]
+    fn degree_one_is_the_control_polygon() {
+        let knots = clamped_knots(POINTS.len(), 1);
+        assert!(close(evaluate(&POINTS, &knots, 0.25).unwrap(), POINTS[1]));
+        assert!(close(
+            evaluate(&POINTS, &knots, 0.375).unwrap(),
+            (15.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn degree_is_lowered_for_few_points() {
+        assert_eq!(clamped_knots(2, 3), [0.0, 0.0, 1.0, 1.0]);
+        assert!(clamped_knots(1, 3).is_empty());
+    }
+
+    #[test]
+    fn mismatched_knots() {
+        assert_eq!(degree(5, 9), Some(3));
+        assert_eq!(degree(5, 6), None);
+        assert_eq!(degree(5, 11), None);
+        assert_eq!(evaluate(&POINTS, &[0.0, 1.0], 0.5), None);
+        assert!(tessellate(&POINTS, &[0.0, 1.0], 8).is_empty());
+    }
+
+    #[test]
+    fn bezier_segments_pass_through_their_joins() {
+        let points = [
+            (0.0, 0.0),
+            (0.0, 40.0),
+            (30.0, 40.0),
+            (30.0, 0.0),
+            (30.0, -40.0),
+            (60.0, -40.0),
+            (60.0, 0.0),
+        ];
+        let knots = bezier_knots(2, 3);
+
+        assert_eq!(degree(points.len(), knots.len()), Some(3));
+        assert!(close(evaluate(&points, &knots, 0.5).unwrap(), points[3]));
+        assert!(close(evaluate(&points, &knots, 1.0).unwrap(), points[6]));
+    }
+}
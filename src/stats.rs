@@ -0,0 +1,322 @@
+//! Statistics on the unknown fields of many files, to help work out what they mean.
+//!
+//! Byte blobs such as `unk_28` are split into `u32` words, read in each file's byte order, so
+//! every word can be compared on its own.
+
+use crate::mneb::{Endian, MNEBFile};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The known fields that unknown fields are compared against.
+pub const KNOWN_FIELDS: [&str; 3] = ["frame_count", "num_control_points", "is_looped"];
+
+/// A value of an unknown field, along with the known fields of where it was found.
+#[derive(Debug, Clone, PartialEq)]
+struct Sample {
+    value: f64,
+    /// How the value is shown, which also tells distinct values apart.
+    key: String,
+    known: [f64; 3],
+}
+
+/// How often a value was seen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// How strongly a field follows a known field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Correlation {
+    pub field: &'static str,
+    /// The Pearson correlation coefficient, or `None` if either field never changes.
+    pub r: Option<f64>,
+}
+
+/// Statistics on one unknown field across a corpus.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldStats {
+    /// The field, such as `curve.unk_8c` or `curve.unk_28[0x04]`.
+    pub name: String,
+    /// How many times the field was seen.
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub distinct: usize,
+    /// The most common values, most common first.
+    pub histogram: Vec<ValueCount>,
+    pub correlations: Vec<Correlation>,
+}
+
+/// The unknown fields of a set of files.
+#[derive(Debug, Default)]
+pub struct Corpus {
+    num_files: usize,
+    fields: BTreeMap<String, Vec<Sample>>,
+}
+
+fn word(bytes: &[u8], endian: Endian) -> u32 {
+    let mut word = [0u8; 4];
+    word[..bytes.len()].copy_from_slice(bytes);
+    match endian {
+        Endian::Big => u32::from_be_bytes(word),
+        Endian::Little => u32::from_le_bytes(word),
+    }
+}
+
+/// The Pearson correlation coefficient of two lists of values.
+fn pearson(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len() as f64;
+    if a.len() < 2 {
+        return None;
+    }
+
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return None;
+    }
+
+    Some(covariance / (variance_a * variance_b).sqrt())
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn num_files(&self) -> usize {
+        self.num_files
+    }
+
+    fn push(&mut self, name: String, value: f64, key: String, known: [f64; 3]) {
+        self.fields
+            .entry(name)
+            .or_default()
+            .push(Sample { value, key, known });
+    }
+
+    fn push_u32(&mut self, name: String, value: u32, known: [f64; 3]) {
+        self.push(name, value as f64, format!("{:#X}", value), known);
+    }
+
+    fn push_f32(&mut self, name: String, value: f32, known: [f64; 3]) {
+        self.push(name, value as f64, value.to_string(), known);
+    }
+
+    /// Splits `bytes` into words, which are named after their offset in the blob.
+    fn push_blob(&mut self, name: &str, bytes: &[u8], endian: Endian, known: [f64; 3]) {
+        for (i, chunk) in bytes.chunks(4).enumerate() {
+            self.push_u32(
+                format!("{}[{:#04X}]", name, i * 4),
+                word(chunk, endian),
+                known,
+            );
+        }
+    }
+
+    /// Adds the unknown fields of `mneb_file` to the corpus.
+    pub fn add(&mut self, mneb_file: &MNEBFile) {
+        self.num_files += 1;
+
+        let frame_count = mneb_file.frame_count as f64;
+        let is_looped = if mneb_file.is_looped { 1.0 } else { 0.0 };
+        let total_points: usize = mneb_file
+            .curves
+            .iter()
+            .map(|curve| curve.control_points.len())
+            .sum();
+
+        let known = [frame_count, total_points as f64, is_looped];
+        self.push_u32("header.unk_8".to_string(), mneb_file._unk_8, known);
+
+        for curve in &mneb_file.curves {
+            let known = [frame_count, curve.control_points.len() as f64, is_looped];
+
+            self.push_blob("curve.unk_28", &curve.unk_28, mneb_file.endian, known);
+            self.push_f32("curve.unk_8c".to_string(), curve.unk_8c, known);
+            self.push_u32("curve.unk_90".to_string(), curve.unk_90, known);
+            self.push_u32("curve.unk_94".to_string(), curve.unk_94, known);
+            self.push_u32("curve.unk_98".to_string(), curve.unk_98, known);
+            for (i, value) in curve.unk_a8.iter().enumerate() {
+                self.push_f32(format!("curve.unk_a8[{}]", i), *value, known);
+            }
+
+            let len = curve.unk_b8.len();
+            self.push(
+                "curve.unk_b8.len".to_string(),
+                len as f64,
+                format!("{:#X}", len),
+                known,
+            );
+            self.push_blob("curve.unk_b8", &curve.unk_b8, mneb_file.endian, known);
+        }
+
+        for set in &mneb_file.demo_option_sets {
+            self.push_blob(
+                "demo_option_set.unk_20",
+                &set.unk_20,
+                mneb_file.endian,
+                known,
+            );
+        }
+    }
+
+    /// Computes statistics for every unknown field that was seen, keeping the `top` most
+    /// common values of each.
+    pub fn stats(&self, top: usize) -> Vec<FieldStats> {
+        self.fields
+            .iter()
+            .map(|(name, samples)| {
+                let mut counts: HashMap<&str, usize> = HashMap::new();
+                for sample in samples {
+                    *counts.entry(&sample.key).or_default() += 1;
+                }
+
+                let mut histogram: Vec<ValueCount> = counts
+                    .iter()
+                    .map(|(&value, &count)| ValueCount {
+                        value: value.to_string(),
+                        count,
+                    })
+                    .collect();
+                histogram.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+                let distinct = histogram.len();
+                histogram.truncate(top);
+
+                let values: Vec<f64> = samples.iter().map(|sample| sample.value).collect();
+                let correlations = KNOWN_FIELDS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &field)| {
+                        let known: Vec<f64> =
+                            samples.iter().map(|sample| sample.known[i]).collect();
+                        Correlation {
+                            field,
+                            r: pearson(&values, &known),
+                        }
+                    })
+                    .collect();
+
+                FieldStats {
+                    name: name.clone(),
+                    count: samples.len(),
+                    min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                    max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                    distinct,
+                    histogram,
+                    correlations,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::{ControlPoint, Curve};
+
+    /// A file with one curve of `num_points` nodes, whose `unk_28` starts with the word 5.
+    fn file(frame_count: u16, num_points: usize, unk_8c: f32, endian: Endian) -> MNEBFile {
+        let mut curve = Curve::new("a", vec![ControlPoint::default(); num_points], Vec::new());
+        curve.unk_8c = unk_8c;
+        curve.unk_28[..4].copy_from_slice(match endian {
+            Endian::Big => &[0, 0, 0, 5],
+            Endian::Little => &[5, 0, 0, 0],
+        });
+
+        let mut mneb_file = MNEBFile::new(vec![curve], frame_count, true);
+        mneb_file.endian = endian;
+        mneb_file
+    }
+
+    fn corpus() -> Corpus {
+        let mut corpus = Corpus::new();
+        corpus.add(&file(60, 2, 1.0, Endian::Big));
+        corpus.add(&file(120, 4, 2.0, Endian::Little));
+        corpus.add(&file(90, 3, 1.5, Endian::Big));
+        corpus.add(&file(60, 2, 1.0, Endian::Little));
+        corpus
+    }
+
+    fn field(stats: &[FieldStats], name: &str) -> FieldStats {
+        stats
+            .iter()
+            .find(|stats| stats.name == name)
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn values_and_histograms() {
+        let corpus = corpus();
+        assert_eq!(corpus.num_files(), 4);
+
+        let stats = corpus.stats(2);
+        let unk_8c = field(&stats, "curve.unk_8c");
+        assert_eq!(unk_8c.count, 4);
+        assert_eq!((unk_8c.min, unk_8c.max), (1.0, 2.0));
+        assert_eq!(unk_8c.distinct, 3);
+        // the most common value first, then by value, keeping only the top 2
+        assert_eq!(
+            unk_8c.histogram,
+            [
+                ValueCount {
+                    value: "1".to_string(),
+                    count: 2
+                },
+                ValueCount {
+                    value: "1.5".to_string(),
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn blobs_are_read_in_each_files_byte_order() {
+        let stats = corpus().stats(5);
+        let word = field(&stats, "curve.unk_28[0x00]");
+
+        assert_eq!(word.distinct, 1);
+        assert_eq!(word.histogram[0].value, "0x5");
+        assert_eq!(
+            field(&stats, "curve.unk_28[0x60]").histogram[0].value,
+            "0x0"
+        );
+    }
+
+    #[test]
+    fn correlations() {
+        let stats = corpus().stats(5);
+        let r = |name: &str| -> Vec<Option<f64>> {
+            field(&stats, name)
+                .correlations
+                .iter()
+                .map(|correlation| correlation.r)
+                .collect()
+        };
+
+        // unk_8c follows the frame count and the number of points exactly, while every file
+        // loops, so nothing can follow that
+        let unk_8c = r("curve.unk_8c");
+        assert!((unk_8c[0].unwrap() - 1.0).abs() < 1e-9);
+        assert!((unk_8c[1].unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(unk_8c[2], None);
+
+        // fields that never change don't correlate with anything
+        assert_eq!(r("curve.unk_90"), [None, None, None]);
+    }
+}
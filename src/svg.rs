@@ -0,0 +1,799 @@
+//! Exporting curves as SVG, either at a single frame or animated with SMIL, and importing
+//! curves from SVG paths.
+//!
+//! Each curve becomes a group holding the evaluated spline, with the class `spline`, and the
+//! control polygon, with the class `control-polygon` and the curve's name as its id.
+
+use crate::animation::Pose;
+use crate::error::SvgError;
+use crate::mneb::{ControlPoint, Curve, KeyFrame, KeyFrameSet, MNEBFile};
+use crate::spline;
+use roxmltree::{Document, Node};
+use std::fmt::Write;
+use svgtypes::{SimplePathSegment, SimplifyingPathParser, Transform};
+
+type Result<T> = std::result::Result<T, SvgError>;
+
+/// How far apart two positions have to be for a change between frames to count as a jump.
+const JUMP_THRESHOLD: f32 = 0.5;
+
+/// Formats a coordinate with at most two decimals.
+fn number(value: f32) -> String {
+    let s = format!("{:.2}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escapes text for use in an attribute.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Builds path data for a polyline.
+fn path_data(points: &[(f32, f32)]) -> String {
+    let mut d = String::new();
+    for (i, &(x, y)) in points.iter().enumerate() {
+        let command = if i == 0 { 'M' } else { 'L' };
+        let _ = write!(d, "{}{} {} ", command, number(x), number(y));
+    }
+
+    d.trim_end().to_string()
+}
+
+/// The view box that holds every point of every curve at each of `frames`, with a margin.
+fn view_box(mneb_file: &MNEBFile, frames: &[f32]) -> String {
+    let mut min = (f32::MAX, f32::MAX);
+    let mut max = (f32::MIN, f32::MIN);
+
+    for &frame in frames {
+        for curve in &mneb_file.curves {
+            let pose = curve.pose(frame);
+            for (x, y) in pose.outline(&curve.knots).into_iter().chain(pose.positions) {
+                min = (min.0.min(x), min.1.min(y));
+                max = (max.0.max(x), max.1.max(y));
+            }
+        }
+    }
+
+    if min.0 > max.0 {
+        return "0 0 1 1".to_string();
+    }
+
+    let margin = (max.0 - min.0).max(max.1 - min.1).max(1.0) * 0.05;
+    format!(
+        "{} {} {} {}",
+        number(min.0 - margin),
+        number(min.1 - margin),
+        number(max.0 - min.0 + margin * 2.0),
+        number(max.1 - min.1 + margin * 2.0)
+    )
+}
+
+fn header(out: &mut String, view_box: &str) {
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{}">"#,
+        view_box
+    );
+}
+
+/// Draws every curve of `mneb_file` at `frame` as an SVG document.
+pub fn to_svg(mneb_file: &MNEBFile, frame: f32) -> String {
+    let mut out = String::new();
+    header(&mut out, &view_box(mneb_file, &[frame]));
+
+    for curve in &mneb_file.curves {
+        let pose = curve.pose(frame);
+        let name = escape(&curve.name);
+
+        let _ = writeln!(out, r#"  <g class="curve">"#);
+        let _ = writeln!(
+            out,
+            r#"    <path id="{}-spline" class="spline" d="{}" fill="none" stroke="rgb(0,121,241)" stroke-width="2" vector-effect="non-scaling-stroke"/>"#,
+            name,
+            path_data(&pose.outline(&curve.knots))
+        );
+        let _ = writeln!(
+            out,
+            r#"    <path id="{}" class="control-polygon" d="{}" fill="none" stroke="rgb(130,130,130)" stroke-width="1" vector-effect="non-scaling-stroke"/>"#,
+            name,
+            path_data(&pose.positions)
+        );
+        let _ = writeln!(out, "  </g>");
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// The frames at which a curve has to be sampled so that linear interpolation between them
+/// reproduces its animation. Every pose between two consecutive key frames is a linear blend
+/// of the poses at those key frames, so sampling at every key frame is enough.
+fn key_times(curve: &Curve, frame_count: f32) -> Vec<f32> {
+    let mut times: Vec<f32> = curve
+        .key_frame_sets
+        .iter()
+        .flat_map(|set| set.key_frames.iter())
+        .map(|key_frame| key_frame.frame as f32)
+        .filter(|&frame| frame > 0.0 && frame < frame_count)
+        .chain([0.0, frame_count])
+        .collect();
+
+    times.sort_by(f32::total_cmp);
+    times.dedup();
+    times
+}
+
+/// Samples `curve` at its key times. Where a node jumps, such as when it's switched off, the
+/// pose just before the jump is sampled too, at the same time.
+fn sample(curve: &Curve, frame_count: f32) -> Vec<(f32, Pose)> {
+    let mut samples = Vec::new();
+
+    for time in key_times(curve, frame_count) {
+        let pose = curve.pose(time);
+
+        if time > 0.0 {
+            let before = curve.pose(time - 0.001);
+            let jumps = before.positions.iter().zip(&pose.positions).any(|(a, b)| {
+                (a.0 - b.0).abs() > JUMP_THRESHOLD || (a.1 - b.1).abs() > JUMP_THRESHOLD
+            });
+
+            if jumps {
+                samples.push((time, before));
+            }
+        }
+
+        samples.push((time, pose));
+    }
+
+    samples
+}
+
+/// Writes an `<animate>` element that moves `attribute` through `values` at `key_times`.
+fn animate(out: &mut String, attribute: &str, values: &[String], key_times: &[f32], timing: &str) {
+    let key_times: Vec<String> = key_times.iter().map(|t| format!("{:.4}", t)).collect();
+    let _ = writeln!(
+        out,
+        r#"      <animate attributeName="{}" values="{}" keyTimes="{}" {}/>"#,
+        attribute,
+        values.join(";"),
+        key_times.join(";"),
+        timing
+    );
+}
+
+/// Draws every curve as an SVG document that plays the animation with SMIL, taking
+/// `frame_count` frames at `framerate` frames per second.
+pub fn to_animated_svg(mneb_file: &MNEBFile, framerate: f32) -> String {
+    let frame_count = (mneb_file.frame_count as f32).max(1.0);
+    let all_frames: Vec<f32> = (0..=mneb_file.frame_count).map(|f| f as f32).collect();
+
+    let timing = format!(
+        r#"dur="{}s" repeatCount="{}" fill="freeze" calcMode="linear""#,
+        number(frame_count / framerate),
+        if mneb_file.is_looped {
+            "indefinite"
+        } else {
+            "1"
+        }
+    );
+
+    let mut out = String::new();
+    header(&mut out, &view_box(mneb_file, &all_frames));
+
+    for curve in &mneb_file.curves {
+        let samples = sample(curve, frame_count);
+        let key_times: Vec<f32> = samples.iter().map(|(t, _)| t / frame_count).collect();
+        let name = escape(&curve.name);
+
+        let _ = writeln!(out, r#"  <g class="curve">"#);
+
+        // the spline and control polygon, with every node moving at once
+        let splines: Vec<String> = samples
+            .iter()
+            .map(|(_, pose)| path_data(&pose.outline(&curve.knots)))
+            .collect();
+        let _ = writeln!(
+            out,
+            r#"    <path id="{}-spline" class="spline" d="{}" fill="none" stroke="rgb(0,121,241)" stroke-width="2" vector-effect="non-scaling-stroke">"#,
+            name, splines[0]
+        );
+        animate(&mut out, "d", &splines, &key_times, &timing);
+        let _ = writeln!(out, "    </path>");
+
+        let polygons: Vec<String> = samples
+            .iter()
+            .map(|(_, pose)| path_data(&pose.positions))
+            .collect();
+        let _ = writeln!(
+            out,
+            r#"    <path id="{}" class="control-polygon" d="{}" fill="none" stroke="rgb(130,130,130)" stroke-width="1" vector-effect="non-scaling-stroke">"#,
+            name, polygons[0]
+        );
+        animate(&mut out, "d", &polygons, &key_times, &timing);
+        let _ = writeln!(out, "    </path>");
+
+        // one marker per key frame set
+        for key_set in &curve.key_frame_sets {
+            let idx = key_set.node_index as usize;
+            let Some(&(x, y)) = samples[0].1.positions.get(idx) else {
+                continue;
+            };
+
+            let xs: Vec<String> = samples
+                .iter()
+                .map(|(_, pose)| number(pose.positions[idx].0))
+                .collect();
+            let ys: Vec<String> = samples
+                .iter()
+                .map(|(_, pose)| number(pose.positions[idx].1))
+                .collect();
+
+            let _ = writeln!(
+                out,
+                r#"    <circle class="node" data-node-index="{}" cx="{}" cy="{}" r="3" fill="rgb(0,228,48)">"#,
+                idx,
+                number(x),
+                number(y)
+            );
+            animate(&mut out, "cx", &xs, &key_times, &timing);
+            animate(&mut out, "cy", &ys, &key_times, &timing);
+            let _ = writeln!(out, "    </circle>");
+        }
+
+        let _ = writeln!(out, "  </g>");
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// How to import an SVG file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgImportOptions {
+    /// The degree of the B-splines built from control polygons, such as the ones written by
+    /// [`to_svg`]. Polygons with too few points get a lower degree. Other paths get the degree
+    /// of their segments.
+    pub degree: usize,
+    /// The speed of SMIL animations, in frames per second.
+    pub framerate: f32,
+    /// Whether to turn SMIL animations of paths into key frame sets.
+    pub animations: bool,
+}
+
+/// What an SVG file became.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgImport {
+    pub mneb_file: MNEBFile,
+    /// The ids of paths that were skipped because they don't have at least two points.
+    pub skipped: Vec<String>,
+}
+
+/// A piece of a path, made of Bézier segments joined end to end.
+#[derive(Debug, Clone, PartialEq)]
+struct Subpath {
+    start: (f64, f64),
+    /// The points of each segment after its start: one for a line, two for a quadratic curve
+    /// and three for a cubic curve.
+    segments: Vec<Vec<(f64, f64)>>,
+}
+
+/// The subpaths of a path. Subpaths without any segments are left out.
+type Subpaths = Vec<Subpath>;
+
+/// Raises the degree of a Bézier segment, given as all of its points, by one without changing
+/// its shape.
+fn elevate(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let n = points.len() as f64;
+    let mut elevated = vec![points[0]];
+    for i in 1..points.len() {
+        let a = i as f64 / n;
+        let (x0, y0) = points[i - 1];
+        let (x1, y1) = points[i];
+        elevated.push((a * x0 + (1.0 - a) * x1, a * y0 + (1.0 - a) * y1));
+    }
+    elevated.push(points[points.len() - 1]);
+    elevated
+}
+
+impl Subpath {
+    /// The highest degree of any segment.
+    fn degree(&self) -> usize {
+        self.segments.iter().map(Vec::len).max().unwrap_or(1)
+    }
+
+    /// Every point of the subpath in order, as a control polygon.
+    fn points(&self) -> Vec<(f64, f64)> {
+        std::iter::once(self.start)
+            .chain(self.segments.iter().flatten().copied())
+            .collect()
+    }
+
+    /// The control points of a B-spline of `degree` that traces the subpath exactly, with
+    /// every segment raised to that degree. Returns `None` if a segment has a higher degree.
+    fn control_points(&self, degree: usize) -> Option<Vec<(f64, f64)>> {
+        let mut points = vec![self.start];
+
+        for segment in &self.segments {
+            if segment.len() > degree {
+                return None;
+            }
+
+            let mut bezier: Vec<(f64, f64)> = std::iter::once(points[points.len() - 1])
+                .chain(segment.iter().copied())
+                .collect();
+            while bezier.len() <= degree {
+                bezier = elevate(&bezier);
+            }
+            points.extend(&bezier[1..]);
+        }
+
+        Some(points)
+    }
+}
+
+/// Combines two transforms, so that `child` is applied first.
+fn combine(parent: &Transform, child: &Transform) -> Transform {
+    Transform::new(
+        parent.a * child.a + parent.c * child.b,
+        parent.b * child.a + parent.d * child.b,
+        parent.a * child.c + parent.c * child.d,
+        parent.b * child.c + parent.d * child.d,
+        parent.a * child.e + parent.c * child.f + parent.e,
+        parent.b * child.e + parent.d * child.f + parent.f,
+    )
+}
+
+/// The transform from an element's coordinates to the document's.
+fn transform_of(node: Node, id: &str) -> Result<Transform> {
+    let mut transform = Transform::default();
+    for ancestor in node.ancestors() {
+        if let Some(text) = ancestor.attribute("transform") {
+            let parent: Transform = text.parse().map_err(|source| SvgError::BadAttribute {
+                id: id.to_string(),
+                attribute: "transform",
+                source,
+            })?;
+            transform = combine(&parent, &transform);
+        }
+    }
+
+    Ok(transform)
+}
+
+/// Reads the segments of path data, in document coordinates.
+fn path_points(d: &str, transform: &Transform, id: &str) -> Result<Subpaths> {
+    let apply = |(x, y): (f64, f64)| {
+        (
+            transform.a * x + transform.c * y + transform.e,
+            transform.b * x + transform.d * y + transform.f,
+        )
+    };
+
+    let mut subpaths: Subpaths = Vec::new();
+    let mut current: Option<Subpath> = None;
+
+    for segment in SimplifyingPathParser::from(d) {
+        let segment = segment.map_err(|source| SvgError::BadAttribute {
+            id: id.to_string(),
+            attribute: "path data",
+            source,
+        })?;
+
+        let points = match segment {
+            SimplePathSegment::MoveTo { x, y } => {
+                subpaths.extend(current.take().filter(|c| !c.segments.is_empty()));
+                current = Some(Subpath {
+                    start: apply((x, y)),
+                    segments: Vec::new(),
+                });
+                continue;
+            }
+            SimplePathSegment::LineTo { x, y } => vec![(x, y)],
+            SimplePathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => vec![(x1, y1), (x2, y2), (x, y)],
+            SimplePathSegment::Quadratic { x1, y1, x, y } => vec![(x1, y1), (x, y)],
+            SimplePathSegment::ClosePath => {
+                // close with a line, unless the subpath already ends where it started
+                let Some(subpath) = &mut current else {
+                    continue;
+                };
+                let end = subpath.segments.last().and_then(|s| s.last().copied());
+                if end.is_some_and(|end| end != subpath.start) {
+                    subpath.segments.push(vec![subpath.start]);
+                }
+                continue;
+            }
+        };
+
+        // path data always starts with a move, but be lenient
+        let subpath = current.get_or_insert_with(|| Subpath {
+            start: apply(points[0]),
+            segments: Vec::new(),
+        });
+        subpath
+            .segments
+            .push(points.into_iter().map(apply).collect());
+    }
+
+    subpaths.extend(current.filter(|c| !c.segments.is_empty()));
+    Ok(subpaths)
+}
+
+/// Rounds a coordinate, reporting it if it doesn't fit in an `i16`.
+fn coordinate(value: f64, id: &str) -> Result<i16> {
+    let rounded = value.round();
+    if rounded.is_finite() && rounded >= i16::MIN as f64 && rounded <= i16::MAX as f64 {
+        Ok(rounded as i16)
+    } else {
+        Err(SvgError::Overflow {
+            id: id.to_string(),
+            value,
+        })
+    }
+}
+
+/// Parses a SMIL clock value, such as `2s`, `500ms` or `1.5`, into seconds.
+fn clock_value(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (number, scale) = if let Some(number) = text.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = text.strip_suffix("min") {
+        (number, 60.0)
+    } else if let Some(number) = text.strip_suffix('h') {
+        (number, 3600.0)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 1.0)
+    } else {
+        (text, 1.0)
+    };
+
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(|seconds| seconds * scale)
+}
+
+/// A SMIL animation of a path's points.
+struct PathAnimation {
+    /// The frame of each value.
+    frames: Vec<u16>,
+    /// The points of each value.
+    values: Vec<Subpaths>,
+    /// The length of the animation, in frames.
+    frame_count: u16,
+    is_looped: bool,
+}
+
+/// Reads the `<animate attributeName="d">` element of a path, if it has one.
+fn path_animation(
+    path: Node,
+    transform: &Transform,
+    id: &str,
+    framerate: f32,
+) -> Result<Option<PathAnimation>> {
+    let Some(animate) = path.children().find(|child| {
+        child.tag_name().name() == "animate" && child.attribute("attributeName") == Some("d")
+    }) else {
+        return Ok(None);
+    };
+
+    let error = |reason: &str| SvgError::BadAnimation {
+        id: id.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let values: Vec<Subpaths> = animate
+        .attribute("values")
+        .ok_or_else(|| error("only animations with `values` are supported"))?
+        .split(';')
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| path_points(value, transform, id))
+        .collect::<Result<_>>()?;
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    let key_times: Vec<f64> = match animate.attribute("keyTimes") {
+        Some(key_times) => key_times
+            .split(';')
+            .map(|time| time.trim().parse::<f64>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| error("`keyTimes` must be a list of numbers"))?,
+        None if values.len() == 1 => vec![0.0],
+        None => (0..values.len())
+            .map(|i| i as f64 / (values.len() - 1) as f64)
+            .collect(),
+    };
+
+    if key_times.len() != values.len() {
+        return Err(error("`keyTimes` and `values` have different lengths"));
+    }
+
+    let duration = animate
+        .attribute("dur")
+        .and_then(clock_value)
+        .ok_or_else(|| error("`dur` must be a positive clock value"))?;
+
+    let to_frame = |seconds: f64| {
+        let frame = (seconds * framerate as f64).round();
+        if (0.0..=u16::MAX as f64).contains(&frame) {
+            Ok(frame as u16)
+        } else {
+            Err(error("the animation is too long"))
+        }
+    };
+
+    let frames = key_times
+        .iter()
+        .map(|time| to_frame(time.clamp(0.0, 1.0) * duration))
+        .collect::<Result<_>>()?;
+
+    Ok(Some(PathAnimation {
+        frames,
+        values,
+        frame_count: to_frame(duration)?,
+        is_looped: animate.attribute("repeatCount") == Some("indefinite")
+            || animate.attribute("repeatDur") == Some("indefinite"),
+    }))
+}
+
+/// Turns the values of an animation into key frame sets for the nodes of one subpath, whose
+/// points are found by `points`, the same way as the path's. Nodes that never move don't get a
+/// key frame set.
+fn key_frame_sets(
+    animation: &PathAnimation,
+    subpath: usize,
+    num_points: usize,
+    points: impl Fn(&Subpath) -> Option<Vec<(f64, f64)>>,
+    id: &str,
+) -> Result<Vec<KeyFrameSet>> {
+    let mut sets = Vec::new();
+
+    for node in 0..num_points {
+        let mut key_frames: Vec<KeyFrame> = Vec::new();
+
+        for (&frame, value) in animation.frames.iter().zip(&animation.values) {
+            let points = value
+                .get(subpath)
+                .and_then(&points)
+                .filter(|points| points.len() == num_points)
+                .ok_or_else(|| SvgError::BadAnimation {
+                    id: id.to_string(),
+                    reason: "every value must have the same segments as the path".to_string(),
+                })?;
+
+            let (x, y) = points[node];
+            let key_frame = KeyFrame::new(frame, true, coordinate(x, id)?, coordinate(y, id)?);
+
+            // when values round to the same frame, the later one wins
+            match key_frames.last_mut() {
+                Some(last) if last.frame == frame => *last = key_frame,
+                _ => key_frames.push(key_frame),
+            }
+        }
+
+        let moves = key_frames
+            .windows(2)
+            .any(|pair| (pair[0].x, pair[0].y) != (pair[1].x, pair[1].y));
+        if moves {
+            sets.push(KeyFrameSet::new(node as u16, key_frames));
+        }
+    }
+
+    Ok(sets)
+}
+
+/// Builds a file from the paths of an SVG document. Each subpath becomes a curve, named after
+/// the path's id, that traces it exactly: its degree is the highest of its segments, lower
+/// segments are raised to that degree, and the knots at the joins between segments are
+/// repeated so the curve passes through them. Straight paths become curves of degree 1.
+///
+/// Paths with the class `control-polygon`, such as the ones written by [`to_svg`], are read as
+/// control points instead, with a clamped, uniform knot vector of
+/// [`SvgImportOptions::degree`]. Paths with the class `spline` are skipped, as are paths
+/// without at least two points.
+///
+/// Since SVG has no notion of inactive nodes, every imported key frame is active.
+pub fn from_svg(text: &str, options: &SvgImportOptions) -> Result<SvgImport> {
+    let document = Document::parse(text)?;
+
+    let mut curves = Vec::new();
+    let mut skipped = Vec::new();
+    let mut frame_count = 0;
+    let mut is_looped = false;
+
+    let paths = document
+        .descendants()
+        .filter(|node| node.tag_name().name() == "path")
+        .filter(|node| {
+            !node
+                .attribute("class")
+                .is_some_and(|class| class.split_whitespace().any(|c| c == "spline"))
+        });
+
+    for (i, path) in paths.enumerate() {
+        let name = path
+            .attribute("id")
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("curve_{}", i));
+
+        let is_polygon = path
+            .attribute("class")
+            .is_some_and(|class| class.split_whitespace().any(|c| c == "control-polygon"));
+
+        let transform = transform_of(path, &name)?;
+        let subpaths = path_points(path.attribute("d").unwrap_or_default(), &transform, &name)?;
+
+        let animation = if options.animations {
+            path_animation(path, &transform, &name, options.framerate)?
+        } else {
+            None
+        };
+
+        if let Some(animation) = &animation {
+            frame_count = frame_count.max(animation.frame_count);
+            is_looped |= animation.is_looped;
+        }
+
+        if subpaths.is_empty() {
+            skipped.push(name);
+            continue;
+        }
+
+        for (j, subpath) in subpaths.iter().enumerate() {
+            let id = if j == 0 {
+                name.clone()
+            } else {
+                format!("{}_{}", name, j)
+            };
+
+            let degree = subpath.degree();
+            let points_of = |subpath: &Subpath| {
+                if is_polygon {
+                    Some(subpath.points())
+                } else {
+                    subpath.control_points(degree)
+                }
+            };
+            let points = points_of(subpath).unwrap_or_default();
+
+            if points.len() > u16::MAX as usize + 1 {
+                return Err(SvgError::TooManyPoints {
+                    id,
+                    count: points.len(),
+                });
+            }
+
+            let control_points = points
+                .iter()
+                .map(|&(x, y)| {
+                    Ok(ControlPoint::new(
+                        coordinate(x, &id)?,
+                        coordinate(y, &id)?,
+                        0,
+                        0,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let knots = if is_polygon {
+                spline::clamped_knots(control_points.len(), options.degree)
+            } else {
+                spline::bezier_knots(subpath.segments.len(), degree)
+            };
+            let mut curve = Curve::new(id.clone(), control_points, knots);
+
+            if let Some(animation) = &animation {
+                curve.key_frame_sets = key_frame_sets(animation, j, points.len(), points_of, &id)?;
+            }
+
+            curves.push(curve);
+        }
+    }
+
+    Ok(SvgImport {
+        mneb_file: MNEBFile::new(curves, frame_count, is_looped),
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::curves;
+
+    const OPTIONS: SvgImportOptions = SvgImportOptions {
+        degree: 3,
+        framerate: 60.0,
+        animations: true,
+    };
+
+    fn import(body: &str) -> SvgImport {
+        let text = format!(r#"<svg xmlns="http://www.w3.org/2000/svg">{}</svg>"#, body);
+        from_svg(&text, &OPTIONS).unwrap()
+    }
+
+    #[test]
+    fn cubic_segments_are_traced_exactly() {
+        let import = import(r#"<path id="a" d="M0 0 C 0 40 30 40 30 0 C 30 -40 60 -40 60 0"/>"#);
+        let curve = &import.mneb_file.curves[0];
+
+        assert_eq!(curve.degree(), Some(3));
+        assert_eq!(curve.control_points.len(), 7);
+
+        // the middle of each segment, and the join between them
+        for (t, expected) in [
+            (0.25, (15.0, 30.0)),
+            (0.5, (30.0, 0.0)),
+            (0.75, (45.0, -30.0)),
+        ] {
+            let (x, y) = curve.evaluate(t).unwrap();
+            assert!((x - expected.0).abs() < 1e-3 && (y - expected.1).abs() < 1e-3);
+        }
+
+        let peak = curve
+            .tessellate(64)
+            .iter()
+            .map(|point| point.1)
+            .fold(f32::MIN, f32::max);
+        assert!((peak - 30.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn lines_have_degree_1() {
+        let import = import(r#"<path id="a" d="M0 0 L 10 0 L 10 10 Z"/>"#);
+        let curve = &import.mneb_file.curves[0];
+
+        assert_eq!(curve.degree(), Some(1));
+        assert_eq!(curve.control_points.len(), 4);
+        assert_eq!(curve.evaluate(1.0 / 6.0), Some((5.0, 0.0)));
+    }
+
+    #[test]
+    fn lines_are_raised_to_the_degree_of_curves() {
+        let import = import(r#"<path id="a" d="M0 0 L 30 0 Q 45 30 60 0"/>"#);
+        let curve = &import.mneb_file.curves[0];
+
+        assert_eq!(curve.degree(), Some(2));
+        assert_eq!(curve.control_points.len(), 5);
+        assert_eq!(curve.evaluate(0.25), Some((15.0, 0.0)));
+        assert_eq!(curve.evaluate(0.75), Some((45.0, 15.0)));
+    }
+
+    #[test]
+    fn degenerate_paths_are_skipped() {
+        let import =
+            import(r#"<path id="dot" d="M5 5"/><path id="empty"/><path id="a" d="M0 0 L 1 1"/>"#);
+
+        assert_eq!(import.skipped, ["dot", "empty"]);
+        assert_eq!(import.mneb_file.curves.len(), 1);
+    }
+
+    #[test]
+    fn exported_control_polygons_round_trip() {
+        let mneb_file = curves();
+        let import = from_svg(&to_svg(&mneb_file, 0.0), &OPTIONS).unwrap();
+
+        for (original, imported) in mneb_file.curves.iter().zip(&import.mneb_file.curves) {
+            assert_eq!(imported.name, original.name);
+            assert_eq!(imported.rest_positions(), original.pose(0.0).positions);
+        }
+    }
+}
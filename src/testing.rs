@@ -0,0 +1,34 @@
+//! Files shared by the unit tests.
+
+// some helpers are only used by the tests of optional modules
+#![allow(dead_code)]
+
+use crate::mneb::{ControlPoint, Curve, KeyFrame, KeyFrameSet, MNEBFile};
+
+/// The big endian curve file in `tests/data`.
+pub(crate) const CURVES: &[u8] = include_bytes!("../tests/data/curves_be.mneb");
+/// The little endian curve file in `tests/data`.
+pub(crate) const CURVES_LE: &[u8] = include_bytes!("../tests/data/curves_le.mneb");
+/// The big endian demo file in `tests/data`, whose option values are padded to 4 bytes.
+pub(crate) const DEMO: &[u8] = include_bytes!("../tests/data/demo_be.mneb");
+/// A big endian curve file with unread bytes between its blocks and tables.
+pub(crate) const CURVES_ODD: &[u8] = include_bytes!("../tests/data/curves_odd_be.mneb");
+
+pub(crate) fn curves() -> MNEBFile {
+    MNEBFile::from_bytes(CURVES).unwrap()
+}
+
+pub(crate) fn curves_le() -> MNEBFile {
+    MNEBFile::from_bytes(CURVES_LE).unwrap()
+}
+
+/// A file with one curve called `a`, made of two nodes at the origin. The second node is moved
+/// by `key_frames`, if there are any.
+pub(crate) fn one_curve(key_frames: Vec<KeyFrame>, frame_count: u16) -> MNEBFile {
+    let mut curve = Curve::new("a", vec![ControlPoint::default(); 2], Vec::new());
+    if !key_frames.is_empty() {
+        curve.key_frame_sets = vec![KeyFrameSet::new(1, key_frames)];
+    }
+
+    MNEBFile::new(vec![curve], frame_count, true)
+}
@@ -0,0 +1,163 @@
+//! Key frame tracks as CSV, with one row per key frame, so they can be edited in spreadsheets.
+
+use crate::error::CsvError;
+use crate::mneb::{KeyFrame, KeyFrameSet, MNEBFile};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::io::{Read, Write};
+
+/// A key frame, along with the curve and node it moves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackRow {
+    pub curve: String,
+    pub node_index: u16,
+    pub frame: u16,
+    #[serde(deserialize_with = "deserialize_bool")]
+    pub is_active: bool,
+    pub x: i16,
+    pub y: i16,
+}
+
+/// Deserializes a boolean the way spreadsheets write them, accepting `true`/`false` in any case
+/// as well as `1`/`0`.
+fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    match text.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(serde::de::Error::custom(format!(
+            "expected `true`, `false`, `1` or `0`, found `{}`",
+            text
+        ))),
+    }
+}
+
+/// Lists every key frame of every curve, in file order.
+pub fn rows(mneb_file: &MNEBFile) -> Vec<TrackRow> {
+    let mut rows = Vec::new();
+
+    for curve in &mneb_file.curves {
+        for key_set in &curve.key_frame_sets {
+            for key_frame in &key_set.key_frames {
+                rows.push(TrackRow {
+                    curve: curve.name.clone(),
+                    node_index: key_set.node_index,
+                    frame: key_frame.frame,
+                    is_active: key_frame.is_active,
+                    x: key_frame.x,
+                    y: key_frame.y,
+                });
+            }
+        }
+    }
+
+    rows
+}
+
+/// Writes every key frame of `mneb_file` as CSV, with a header row.
+pub fn write_csv<W: Write>(mneb_file: &MNEBFile, writer: W) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for row in rows(mneb_file) {
+        writer.serialize(row)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Replaces the key frame sets of every curve in `mneb_file` with the ones read from CSV
+/// written by [`write_csv`]. Rows for the same curve and node form one key frame set, with key
+/// frames kept in the order they're listed.
+///
+/// Nothing is changed if a row names a curve that doesn't exist, or a node past the end of its
+/// curve's control points.
+pub fn read_csv<R: Read>(mneb_file: &mut MNEBFile, reader: R) -> Result<(), CsvError> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+
+    let mut key_frame_sets: Vec<Vec<KeyFrameSet>> = vec![Vec::new(); mneb_file.curves.len()];
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let row: TrackRow = record.deserialize(Some(&headers))?;
+
+        let Some(index) = mneb_file
+            .curves
+            .iter()
+            .position(|curve| curve.name == row.curve)
+        else {
+            return Err(CsvError::UnknownCurve {
+                line,
+                curve: row.curve,
+            });
+        };
+
+        let count = mneb_file.curves[index].control_points.len();
+        if row.node_index as usize >= count {
+            return Err(CsvError::NodeOutOfRange {
+                line,
+                curve: row.curve,
+                node_index: row.node_index,
+                count,
+            });
+        }
+
+        let key_frame = KeyFrame::new(row.frame, row.is_active, row.x, row.y);
+        let sets = &mut key_frame_sets[index];
+        match sets.iter_mut().find(|set| set.node_index == row.node_index) {
+            Some(set) => set.key_frames.push(key_frame),
+            None => sets.push(KeyFrameSet::new(row.node_index, vec![key_frame])),
+        }
+    }
+
+    for (curve, sets) in mneb_file.curves.iter_mut().zip(key_frame_sets) {
+        curve.key_frame_sets = sets;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{curves_le, one_curve};
+
+    #[test]
+    fn spreadsheet_booleans() {
+        let csv = "curve,node_index,frame,is_active,x,y\n\
+                   a,1,0,TRUE,1,2\n\
+                   a,1,10,False,0,0\n\
+                   a,1,20,1,3,4\n\
+                   a,1,30,0,0,0\n";
+
+        let mut mneb_file = one_curve(Vec::new(), 60);
+        read_csv(&mut mneb_file, csv.as_bytes()).unwrap();
+
+        let states: Vec<bool> = mneb_file.curves[0].key_frame_sets[0]
+            .key_frames
+            .iter()
+            .map(|key_frame| key_frame.is_active)
+            .collect();
+        assert_eq!(states, [true, false, true, false]);
+    }
+
+    #[test]
+    fn bad_booleans_are_rejected() {
+        let csv = "curve,node_index,frame,is_active,x,y\na,1,0,yes,1,2\n";
+        assert!(read_csv(&mut one_curve(Vec::new(), 60), csv.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut mneb_file = curves_le();
+        let original = mneb_file.clone();
+
+        let mut csv = Vec::new();
+        write_csv(&mneb_file, &mut csv).unwrap();
+        read_csv(&mut mneb_file, csv.as_slice()).unwrap();
+        assert_eq!(mneb_file, original);
+    }
+}
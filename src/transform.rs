@@ -0,0 +1,289 @@
+//! Affine transforms of curves, which move their control points and key frames together.
+
+use crate::error::TransformError;
+use crate::mneb::{Curve, MNEBFile};
+
+/// An affine transform of x and y, along with a separate scale and offset for z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// The linear part, so that `x' = matrix[0][0] * x + matrix[0][1] * y + translation.0`.
+    pub matrix: [[f32; 2]; 2],
+    pub translation: (f32, f32),
+    pub z_scale: f32,
+    pub z_offset: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    /// The transform that leaves everything where it is.
+    pub const IDENTITY: Self = Self {
+        matrix: [[1.0, 0.0], [0.0, 1.0]],
+        translation: (0.0, 0.0),
+        z_scale: 1.0,
+        z_offset: 0.0,
+    };
+
+    pub fn translate(x: f32, y: f32) -> Self {
+        Self {
+            translation: (x, y),
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self {
+            matrix: [[x, 0.0], [0.0, y]],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Rotates by `degrees` around the origin, turning the x axis towards the y axis.
+    pub fn rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            matrix: [[cos, -sin], [sin, cos]],
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Negates x, mirroring left and right.
+    pub fn mirror_x() -> Self {
+        Self::scale(-1.0, 1.0)
+    }
+
+    /// Negates y, mirroring up and down.
+    pub fn mirror_y() -> Self {
+        Self::scale(1.0, -1.0)
+    }
+
+    pub fn translate_z(z: f32) -> Self {
+        Self {
+            z_offset: z,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale_z(z: f32) -> Self {
+        Self {
+            z_scale: z,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// The transform that applies `self` and then `next`.
+    pub fn then(&self, next: &Self) -> Self {
+        let a = &next.matrix;
+        let b = &self.matrix;
+        let (x, y) = next.apply(self.translation);
+
+        Self {
+            matrix: [
+                [
+                    a[0][0] * b[0][0] + a[0][1] * b[1][0],
+                    a[0][0] * b[0][1] + a[0][1] * b[1][1],
+                ],
+                [
+                    a[1][0] * b[0][0] + a[1][1] * b[1][0],
+                    a[1][0] * b[0][1] + a[1][1] * b[1][1],
+                ],
+            ],
+            translation: (x, y),
+            z_scale: next.z_scale * self.z_scale,
+            z_offset: next.z_scale * self.z_offset + next.z_offset,
+        }
+    }
+
+    /// The same transform, but centered on `origin` instead of `(0, 0)`.
+    pub fn around(&self, origin: (f32, f32)) -> Self {
+        Self::translate(-origin.0, -origin.1)
+            .then(self)
+            .then(&Self::translate(origin.0, origin.1))
+    }
+
+    /// Transforms an x and y position.
+    pub fn apply(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let m = &self.matrix;
+        (
+            m[0][0] * x + m[0][1] * y + self.translation.0,
+            m[1][0] * x + m[1][1] * y + self.translation.1,
+        )
+    }
+
+    /// Transforms a z position.
+    pub fn apply_z(&self, z: f32) -> f32 {
+        self.z_scale * z + self.z_offset
+    }
+}
+
+/// Rounds `value` to an `i16`, or describes where it overflowed.
+fn to_i16(value: f32, curve: &Curve, item: impl FnOnce() -> String) -> Result<i16, TransformError> {
+    let rounded = value.round();
+    if (i16::MIN as f32..=i16::MAX as f32).contains(&rounded) {
+        Ok(rounded as i16)
+    } else {
+        Err(TransformError::Overflow {
+            curve: curve.name.clone(),
+            item: item(),
+            value,
+        })
+    }
+}
+
+/// A copy of `curve` with `transform` applied to its control points and active key frames.
+/// Inactive key frames keep their positions, since nodes ignore them while switched off.
+pub fn transform_curve(curve: &Curve, transform: &Transform) -> Result<Curve, TransformError> {
+    let mut transformed = curve.clone();
+
+    for (i, point) in transformed.control_points.iter_mut().enumerate() {
+        let (x, y) = transform.apply((point.x as f32, point.y as f32));
+        let z = transform.apply_z(point.z as f32);
+        let item = || format!("control point {}", i);
+
+        point.x = to_i16(x, curve, item)?;
+        point.y = to_i16(y, curve, item)?;
+        point.z = to_i16(z, curve, item)?;
+    }
+
+    for set in &mut transformed.key_frame_sets {
+        for (i, key_frame) in set.key_frames.iter_mut().enumerate() {
+            if !key_frame.is_active {
+                continue;
+            }
+
+            let (x, y) = transform.apply((key_frame.x as f32, key_frame.y as f32));
+            let item = || format!("key frame {} of node {}", i, set.node_index);
+
+            key_frame.x = to_i16(x, curve, item)?;
+            key_frame.y = to_i16(y, curve, item)?;
+        }
+    }
+
+    Ok(transformed)
+}
+
+/// Applies `transform` to the curves of `mneb_file` called `names`, or to every curve if
+/// `names` is empty. If any position would overflow, the file is left as it was.
+pub fn transform_curves(
+    mneb_file: &mut MNEBFile,
+    names: &[&str],
+    transform: &Transform,
+) -> Result<(), TransformError> {
+    if let Some(&name) = names.iter().find(|&&name| mneb_file.curve(name).is_none()) {
+        return Err(TransformError::CurveNotFound {
+            name: name.to_string(),
+        });
+    }
+
+    let curves = mneb_file
+        .curves
+        .iter()
+        .map(|curve| {
+            if names.is_empty() || names.contains(&curve.name.as_str()) {
+                transform_curve(curve, transform)
+            } else {
+                Ok(curve.clone())
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    mneb_file.curves = curves;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mneb::{ControlPoint, KeyFrame};
+    use crate::testing::{curves, one_curve};
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4
+    }
+
+    #[test]
+    fn rotation_turns_x_towards_y() {
+        assert!(close(Transform::rotate(90.0).apply((1.0, 0.0)), (0.0, 1.0)));
+        assert!(close(
+            Transform::rotate(90.0).apply((0.0, 1.0)),
+            (-1.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn then_applies_self_first() {
+        let transform = Transform::scale(2.0, 2.0).then(&Transform::translate(1.0, 0.0));
+        assert!(close(transform.apply((1.0, 1.0)), (3.0, 2.0)));
+
+        let transform = Transform::translate(1.0, 0.0).then(&Transform::scale(2.0, 2.0));
+        assert!(close(transform.apply((1.0, 1.0)), (4.0, 2.0)));
+
+        let transform = Transform::scale_z(2.0).then(&Transform::translate_z(1.0));
+        assert_eq!(transform.apply_z(3.0), 7.0);
+    }
+
+    #[test]
+    fn rotation_around_a_point_keeps_it_in_place() {
+        let transform = Transform::rotate(90.0).around((10.0, 10.0));
+        assert!(close(transform.apply((10.0, 10.0)), (10.0, 10.0)));
+        assert!(close(transform.apply((11.0, 10.0)), (10.0, 11.0)));
+    }
+
+    #[test]
+    fn inactive_key_frames_are_skipped() {
+        let mut mneb_file = one_curve(
+            vec![KeyFrame::new(0, true, 1, 2), KeyFrame::new(10, false, 1, 2)],
+            60,
+        );
+        mneb_file.curves[0].control_points[0] = ControlPoint::new(1, 2, 3, 0);
+
+        let transform = Transform::translate(10.0, 20.0).then(&Transform::translate_z(5.0));
+        transform_curves(&mut mneb_file, &[], &transform).unwrap();
+
+        let curve = &mneb_file.curves[0];
+        assert_eq!(curve.control_points[0], ControlPoint::new(11, 22, 8, 0));
+        assert_eq!(
+            curve.key_frame_sets[0].key_frames,
+            [
+                KeyFrame::new(0, true, 11, 22),
+                KeyFrame::new(10, false, 1, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn overflow_leaves_the_file_unchanged() {
+        // `a` moves without trouble, but `b` is already near the edge
+        let mut mneb_file = one_curve(Vec::new(), 60);
+        let b = Curve::new(
+            "b",
+            vec![ControlPoint::default(), ControlPoint::new(0, 30000, 0, 0)],
+            Vec::new(),
+        );
+        mneb_file.curves.push(b);
+        let original = mneb_file.clone();
+
+        let error =
+            transform_curves(&mut mneb_file, &[], &Transform::translate(0.0, 10000.0)).unwrap_err();
+        assert!(matches!(
+            error,
+            TransformError::Overflow { curve, item, value }
+                if curve == "b" && item == "control point 1" && value == 40000.0
+        ));
+        assert_eq!(mneb_file, original);
+    }
+
+    #[test]
+    fn unknown_curves_are_errors() {
+        let mut mneb_file = curves();
+        let original = mneb_file.clone();
+
+        let error = transform_curves(&mut mneb_file, &["nope"], &Transform::IDENTITY).unwrap_err();
+        assert!(matches!(error, TransformError::CurveNotFound { name } if name == "nope"));
+        assert_eq!(mneb_file, original);
+    }
+}
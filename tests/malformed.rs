@@ -0,0 +1,61 @@
+use mneb::{MNEBFile, MnebError};
+
+/// Builds a big endian curve file whose key frame table has `num_entries` offsets, all
+/// pointing at the same key frame set of `num_key_frames` key frames.
+fn reused_key_frame_set(num_entries: u32, num_key_frames: u16) -> Vec<u8> {
+    let mut raw = Vec::new();
+    raw.extend(b"MNCH");
+    raw.extend(0x18u32.to_be_bytes());
+    raw.extend(0u32.to_be_bytes()); // unk_8
+    raw.extend(1u32.to_be_bytes()); // curves
+    raw.extend(0u32.to_be_bytes()); // lock
+    raw.extend(60u16.to_be_bytes()); // frame count
+    raw.extend([1, 0]);
+
+    let start = raw.len() as u32;
+    raw.extend(b"MNCN");
+    raw.extend(0u32.to_be_bytes()); // block size, filled in below
+    raw.extend([0u8; 0x20 + 0x64 + 0x10]); // name and unknown fields
+
+    let tables = start + 0xB8;
+    raw.extend(tables.to_be_bytes()); // control points
+    raw.extend((tables + 4).to_be_bytes()); // knots
+    raw.extend((tables + 8).to_be_bytes()); // key frame info
+    raw.extend([0u8; 0x10]); // unk_a8
+
+    raw.extend(0u32.to_be_bytes());
+    raw.extend(0u32.to_be_bytes());
+    raw.extend((tables + 12).to_be_bytes());
+
+    let set = tables + 16 + num_entries * 4;
+    raw.extend(num_entries.to_be_bytes());
+    for _ in 0..num_entries {
+        raw.extend(set.to_be_bytes());
+    }
+
+    raw.extend(0u16.to_be_bytes());
+    raw.extend(num_key_frames.to_be_bytes());
+    for frame in 0..num_key_frames {
+        raw.extend(frame.to_be_bytes());
+        raw.extend([1, 0, 0, 0, 0, 0]);
+    }
+
+    let block_size = raw.len() as u32 - start;
+    raw[start as usize + 4..start as usize + 8].copy_from_slice(&block_size.to_be_bytes());
+    raw
+}
+
+#[test]
+fn single_key_frame_set_parses() {
+    let mneb_file = MNEBFile::from_bytes(&reused_key_frame_set(1, 100)).unwrap();
+    assert_eq!(mneb_file.curves[0].key_frame_sets[0].key_frames.len(), 100);
+}
+
+#[test]
+fn reused_key_frame_sets_are_rejected() {
+    let raw = reused_key_frame_set(400, u16::MAX);
+    assert!(matches!(
+        MNEBFile::from_bytes(&raw),
+        Err(MnebError::OverlappingTables { .. })
+    ));
+}
@@ -0,0 +1,64 @@
+use mneb::{Endian, MNEBFile};
+
+fn round_trip(raw: &[u8], endian: Endian) {
+    let mneb_file = MNEBFile::from_bytes(raw).unwrap();
+    assert_eq!(mneb_file.endian, endian);
+    assert_eq!(mneb_file.to_bytes().unwrap(), raw);
+}
+
+#[test]
+fn curves_big_endian() {
+    round_trip(include_bytes!("data/curves_be.mneb"), Endian::Big);
+}
+
+#[test]
+fn curves_little_endian() {
+    round_trip(include_bytes!("data/curves_le.mneb"), Endian::Little);
+}
+
+#[test]
+fn demo_big_endian() {
+    round_trip(include_bytes!("data/demo_be.mneb"), Endian::Big);
+}
+
+#[test]
+fn demo_little_endian() {
+    round_trip(include_bytes!("data/demo_le.mneb"), Endian::Little);
+}
+
+#[test]
+fn json_round_trip() {
+    let raw = include_bytes!("data/curves_le.mneb");
+    let mneb_file = MNEBFile::from_bytes(raw).unwrap();
+    let json = serde_json::to_string(&mneb_file).unwrap();
+    let imported: MNEBFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(imported.to_bytes().unwrap(), raw);
+}
+
+// laid out differently from the writer: unread bytes after the header and between tables,
+// tables out of order, non-zero padding bytes in key frames, and bytes after the last block
+#[test]
+fn curves_with_their_own_layout() {
+    round_trip(include_bytes!("data/curves_odd_be.mneb"), Endian::Big);
+}
+
+// option values that aren't padded to 4 bytes, and bytes after the MNDD block
+#[test]
+fn demo_with_its_own_layout() {
+    round_trip(include_bytes!("data/demo_odd_le.mneb"), Endian::Little);
+}
+
+#[test]
+fn edited_files_are_laid_out_again() {
+    let raw = include_bytes!("data/curves_odd_be.mneb");
+    let mut mneb_file = MNEBFile::from_bytes(raw).unwrap();
+    mneb_file.curves[0].key_frame_sets[0].key_frames[0].x += 1;
+
+    let bytes = mneb_file.to_bytes().unwrap();
+    assert_ne!(bytes, raw);
+    assert_eq!(MNEBFile::from_bytes(&bytes).unwrap(), mneb_file);
+
+    // undoing the edit brings back the original bytes
+    mneb_file.curves[0].key_frame_sets[0].key_frames[0].x -= 1;
+    assert_eq!(mneb_file.to_bytes().unwrap(), raw);
+}